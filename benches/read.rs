@@ -0,0 +1,97 @@
+//! Compare the throughput of the CSV readers
+//!
+//! Generates a CSV file of `DAB_BENCH_MB` megabytes, 1024 by default, in the temporary directory and reads it with
+//! every reader, converting the records to transactions. Readers are also run through `into_rows`, as the command
+//! line does to report rejected rows along with their text:
+//!
+//! ```text
+//! DAB_BENCH_MB=4096 cargo bench --bench read
+//! ```
+
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+use dab::{
+    input::{self, CsvDialect, MmapCsvReader, SourceRow},
+    CsvReader, ParallelCsvReader, Reader, Transaction, TransactionRow,
+};
+
+fn generate(path: &Path, size: u64) -> std::io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "type,client,tx,amount")?;
+
+    let mut written = 0;
+    let mut tx = 0u32;
+    while written < size {
+        tx = tx.wrapping_add(1);
+        let client = tx % 65_521;
+        let row = match tx % 10 {
+            0 => format!("withdrawal,{client},{tx},{}.{:04}\n", tx % 100, tx % 10_000),
+            9 => format!("dispute,{client},{},\n", tx - 1),
+            _ => format!("deposit,{client},{tx},{}.{:02}\n", tx % 1_000, tx % 100),
+        };
+        writer.write_all(row.as_bytes())?;
+        written += row.len() as u64;
+    }
+
+    writer.flush()
+}
+
+fn bench<R: Reader>(name: &str, size: u64, reader: impl FnOnce() -> anyhow::Result<R>) {
+    let start = Instant::now();
+    let transactions = input::read(reader().unwrap()).filter(Result::is_ok).count();
+    let elapsed = start.elapsed();
+
+    let throughput = size as f64 / (1 << 20) as f64 / elapsed.as_secs_f64();
+    println!("{name:<24} {transactions} transactions in {elapsed:.2?} ({throughput:.0} MiB/s)");
+}
+
+fn bench_rows<I, E>(name: &str, size: u64, rows: impl FnOnce() -> anyhow::Result<I>)
+where
+    I: Iterator<Item = (SourceRow, Result<TransactionRow, E>)>,
+{
+    let start = Instant::now();
+    let transactions = rows()
+        .unwrap()
+        .filter_map(|(_, record)| record.ok())
+        .filter_map(|record| TryInto::<Transaction>::try_into(record).ok())
+        .count();
+    let elapsed = start.elapsed();
+
+    let throughput = size as f64 / (1 << 20) as f64 / elapsed.as_secs_f64();
+    println!("{name:<24} {transactions} transactions in {elapsed:.2?} ({throughput:.0} MiB/s)");
+}
+
+fn main() {
+    let megabytes = std::env::var("DAB_BENCH_MB")
+        .ok()
+        .and_then(|megabytes| megabytes.parse().ok())
+        .unwrap_or(1024u64);
+
+    let path = std::env::temp_dir().join("dab-bench-read.csv");
+    generate(&path, megabytes << 20).unwrap();
+    let size = std::fs::metadata(&path).unwrap().len();
+    let dialect = CsvDialect::default();
+    let threads = std::thread::available_parallelism().map_or(4, usize::from);
+
+    bench("csv", size, || CsvReader::<u16, u32>::new(&path));
+    bench("mmap", size, || {
+        MmapCsvReader::<u16, u32>::new(&path, &dialect)
+    });
+    bench(&format!("parallel ({threads} threads)"), size, || {
+        ParallelCsvReader::<u16, u32>::new(&path, &dialect, threads)
+    });
+
+    bench_rows("csv rows", size, || {
+        CsvReader::<u16, u32>::new(&path).map(CsvReader::into_rows)
+    });
+    bench_rows("mmap rows", size, || {
+        MmapCsvReader::<u16, u32>::new(&path, &dialect).map(MmapCsvReader::into_rows)
+    });
+
+    std::fs::remove_file(&path).unwrap();
+}
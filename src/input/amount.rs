@@ -0,0 +1,431 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::transaction;
+
+pub use crate::transaction::MAX_DECIMALS;
+
+/// What to do with amounts that have more than [`MAX_DECIMALS`] decimal places
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Precision {
+    /// Reject the transaction
+    #[default]
+    Reject,
+
+    /// Round the amount half away from zero to [`MAX_DECIMALS`] decimal places
+    Round,
+}
+
+/// Error raised when parsing an unknown [`Precision`]
+#[derive(Debug, Error)]
+#[error("unknown precision policy `{0}`, expected one of `reject` or `round`")]
+pub struct ParsePrecisionError(String);
+
+impl FromStr for Precision {
+    type Err = ParsePrecisionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Precision::Reject),
+            "round" => Ok(Precision::Round),
+            _ => Err(ParsePrecisionError(s.to_string())),
+        }
+    }
+}
+
+/// How amounts written as text are parsed, see [`parse`]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Locale {
+    /// Plain numbers such as `1234.56`
+    #[default]
+    Plain,
+
+    /// `.` decimal separator with `,`, space or `'` thousands separators, such as `1,234.56`
+    Point,
+
+    /// `,` decimal separator with `.`, space or `'` thousands separators, such as `1 234,56`
+    Comma,
+
+    /// Guess the decimal separator of every amount, rejecting the ones where it is ambiguous such as `1,234`
+    Auto,
+}
+
+/// Error raised when parsing an unknown [`Locale`]
+#[derive(Debug, Error)]
+#[error("unknown locale `{0}`, expected one of `plain`, `point`, `comma` or `auto`")]
+pub struct ParseLocaleError(String);
+
+impl FromStr for Locale {
+    type Err = ParseLocaleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Locale::Plain),
+            "point" => Ok(Locale::Point),
+            "comma" => Ok(Locale::Comma),
+            "auto" => Ok(Locale::Auto),
+            _ => Err(ParseLocaleError(s.to_string())),
+        }
+    }
+}
+
+/// Error raised when an amount written as text cannot be parsed
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+#[non_exhaustive]
+pub enum ParseAmountError {
+    #[error("invalid amount `{0}`")]
+    Invalid(String),
+
+    #[error("ambiguous amount `{0}`, `{1}` may either be a decimal or a thousands separator")]
+    Ambiguous(String, char),
+}
+
+/// The amount of a transaction row, as stored in the file it has been read from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Amount {
+    /// An amount stored as a number, such as in a JSON number or a Parquet `DOUBLE` column
+    Number(f64),
+
+    /// An amount stored as text, parsed according to a [`Locale`]
+    Text(String),
+}
+
+impl Amount {
+    /// Value of the amount, parsing amounts stored as text according to the `locale`
+    pub fn value(&self, locale: Locale) -> Result<f64, ParseAmountError> {
+        match self {
+            Amount::Number(amount) => Ok(*amount),
+            Amount::Text(text) => parse(text, locale),
+        }
+    }
+}
+
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Amount::Number(amount) => write!(f, "{amount}"),
+            Amount::Text(text) => write!(f, "{text}"),
+        }
+    }
+}
+
+/// Currency symbols allowed before or after an amount
+const CURRENCY_SYMBOLS: &[char] = &[
+    '$', '€', '£', '¥', '₹', '₽', '₩', '₺', '₪', '₫', '₴', '₦', '฿', '¢', '¤',
+];
+
+/// Characters separating groups of thousands, besides `.` and `,`
+const GROUP_SEPARATORS: &[char] = &[' ', '\u{a0}', '\u{202f}', '\''];
+
+/// Parse an amount written as `text` according to the `locale`
+///
+/// Besides [`Locale::Plain`], amounts may have thousands separators and a currency symbol or a three-letter
+/// currency code, such as `€12.50`, `-12,50 EUR` or `USD 1,234.56`
+pub fn parse(text: &str, locale: Locale) -> Result<f64, ParseAmountError> {
+    let invalid = || ParseAmountError::Invalid(text.to_string());
+
+    if locale == Locale::Plain {
+        return text.trim().parse().map_err(|_| invalid());
+    }
+
+    let (negative, number) = split_currency(text.trim()).ok_or_else(invalid)?;
+    if number.is_empty()
+        || !number
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == ',' || GROUP_SEPARATORS.contains(&c))
+    {
+        return Err(invalid());
+    }
+
+    let decimal = match locale {
+        Locale::Point => Some('.'),
+        Locale::Comma => Some(','),
+        _ => guess_decimal(number).map_err(|c| ParseAmountError::Ambiguous(text.to_string(), c))?,
+    };
+
+    let (integer, fraction) = match decimal.and_then(|decimal| number.split_once(decimal)) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (number, None),
+    };
+
+    let integer = ungroup(integer).ok_or_else(invalid)?;
+    let fraction = match fraction {
+        Some(fraction) if !fraction.is_empty() && fraction.chars().all(|c| c.is_ascii_digit()) => {
+            fraction
+        }
+        Some(_) => return Err(invalid()),
+        None if integer.is_empty() => return Err(invalid()),
+        None => "0",
+    };
+
+    let sign = if negative { "-" } else { "" };
+    format!("{sign}{integer}.{fraction}")
+        .parse()
+        .map_err(|_| invalid())
+}
+
+/// Split the sign and the currency off an amount, returning whether the amount is negative along with its number
+fn split_currency(text: &str) -> Option<(bool, &str)> {
+    let mut rest = text;
+    let mut sign = None;
+    let mut currency = false;
+
+    loop {
+        if sign.is_none() {
+            if let Some(stripped) = rest.strip_prefix(['-', '+']) {
+                sign = Some(rest.starts_with('-'));
+                rest = stripped.trim_start();
+                continue;
+            }
+        }
+
+        if !currency {
+            if let Some(stripped) = strip_currency(rest, true) {
+                currency = true;
+                rest = stripped.trim_start();
+                continue;
+            }
+        }
+
+        break;
+    }
+
+    if !currency {
+        if let Some(stripped) = strip_currency(rest, false) {
+            rest = stripped.trim_end();
+        }
+    }
+
+    // A sign between the currency and the number, such as in `€-12.50`, is fine but not twice
+    if rest.starts_with(['-', '+']) {
+        return None;
+    }
+
+    Some((sign.unwrap_or(false), rest))
+}
+
+/// Strip a currency symbol or a three-letter currency code from the start or the end of `text`
+fn strip_currency(text: &str, start: bool) -> Option<&str> {
+    if start {
+        if let Some(stripped) = text.strip_prefix(CURRENCY_SYMBOLS) {
+            return Some(stripped);
+        }
+    } else if let Some(stripped) = text.strip_suffix(CURRENCY_SYMBOLS) {
+        return Some(stripped);
+    }
+
+    let code = |code: &str| code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase());
+    if start {
+        let end = text
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(text.len());
+        code(&text[..end]).then(|| &text[end..])
+    } else {
+        let start = text
+            .rfind(|c: char| !c.is_ascii_alphabetic())
+            .map_or(0, |i| i + 1);
+        code(&text[start..]).then(|| &text[..start])
+    }
+}
+
+/// Guess the decimal separator of `number`, or return the separator that may either be a decimal or a thousands
+/// separator if it cannot be told
+fn guess_decimal(number: &str) -> Result<Option<char>, char> {
+    let points = number.matches('.').count();
+    let commas = number.matches(',').count();
+
+    match (points, commas) {
+        (0, 0) => Ok(None),
+
+        // A single separator is a decimal separator unless it may group the thousands of a number below 10000
+        (1, 0) | (0, 1) => {
+            let separator = if points == 1 { '.' } else { ',' };
+            let (integer, fraction) = number.split_once(separator).unwrap_or_default();
+
+            let grouped = integer.contains(GROUP_SEPARATORS);
+            if fraction.len() == 3 && !grouped && (1..=3).contains(&integer.len()) && integer != "0"
+            {
+                Err(separator)
+            } else {
+                Ok(Some(separator))
+            }
+        }
+
+        // A separator used several times groups thousands
+        (_, 0) | (0, _) => Ok(None),
+
+        // Both separators are used, thousands separators come first
+        _ => Ok(number
+            .rfind(['.', ','])
+            .and_then(|i| number[i..].chars().next())),
+    }
+}
+
+/// Remove the thousands separators of the integer part of an amount, checking that they group digits by three
+fn ungroup(integer: &str) -> Option<String> {
+    let mut separators = integer.matches(|c: char| !c.is_ascii_digit());
+    let Some(separator) = separators.next() else {
+        return Some(integer.to_string());
+    };
+    if separators.any(|other| other != separator) {
+        return None;
+    }
+
+    let mut groups = integer.split(separator);
+    let first = groups.next()?;
+    if !(1..=3).contains(&first.len()) {
+        return None;
+    }
+
+    let mut digits = first.to_string();
+    for group in groups {
+        if group.len() != 3 {
+            return None;
+        }
+        digits.push_str(group);
+    }
+
+    Some(digits)
+}
+
+/// Error raised when the amount of a transaction row is invalid
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum AmountError {
+    #[error("{0} transaction should have an amount")]
+    Missing(&'static str),
+
+    #[error("{kind} transaction should not have an amount, found {amount}")]
+    Unexpected { kind: &'static str, amount: Amount },
+
+    #[error(transparent)]
+    Parse(#[from] ParseAmountError),
+
+    #[error("amount should be a finite number, found {0}")]
+    NotFinite(f64),
+
+    #[error("amount should be positive, found {0}")]
+    NotPositive(f64),
+
+    #[error("amount should have at most {MAX_DECIMALS} decimal places, found {0}")]
+    TooPrecise(f64),
+}
+
+/// Validate the `amount` of a deposit or a withdrawal, rounding it according to the `precision` policy
+pub fn validate(amount: f64, precision: Precision) -> Result<f64, AmountError> {
+    if !amount.is_finite() {
+        return Err(AmountError::NotFinite(amount));
+    }
+
+    let amount = if transaction::is_precise(amount) {
+        amount
+    } else {
+        match precision {
+            Precision::Reject => return Err(AmountError::TooPrecise(amount)),
+            Precision::Round => {
+                let scale = 10f64.powi(MAX_DECIMALS as i32);
+                (amount * scale).round() / scale
+            }
+        }
+    };
+
+    // Checked after rounding so that amounts rounded down to zero are rejected as well
+    if amount <= 0.0 {
+        return Err(AmountError::NotPositive(amount));
+    }
+
+    Ok(amount)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_amounts() {
+        assert_eq!(validate(1.5, Precision::Reject), Ok(1.5));
+        assert_eq!(validate(0.0001, Precision::Reject), Ok(0.0001));
+        assert_eq!(validate(1234.5678, Precision::Reject), Ok(1234.5678));
+
+        assert_eq!(
+            validate(0.0, Precision::Reject),
+            Err(AmountError::NotPositive(0.0))
+        );
+        assert_eq!(
+            validate(-1.0, Precision::Round),
+            Err(AmountError::NotPositive(-1.0))
+        );
+        assert!(matches!(
+            validate(f64::NAN, Precision::Reject),
+            Err(AmountError::NotFinite(_))
+        ));
+        assert_eq!(
+            validate(f64::INFINITY, Precision::Round),
+            Err(AmountError::NotFinite(f64::INFINITY))
+        );
+
+        assert_eq!(
+            validate(1.23456, Precision::Reject),
+            Err(AmountError::TooPrecise(1.23456))
+        );
+        assert_eq!(validate(1.23456, Precision::Round), Ok(1.2346));
+
+        // Amounts rounded down to zero are rejected
+        assert_eq!(
+            validate(0.00001, Precision::Round),
+            Err(AmountError::NotPositive(0.0))
+        );
+    }
+
+    #[test]
+    fn parse_locales() {
+        assert_eq!(parse("1234.56", Locale::Plain), Ok(1234.56));
+        assert!(parse("1,234.56", Locale::Plain).is_err());
+
+        assert_eq!(parse("1,234.56", Locale::Point), Ok(1234.56));
+        assert_eq!(parse("€12.50", Locale::Point), Ok(12.5));
+        assert_eq!(parse("-$ 1'234", Locale::Point), Ok(-1234.0));
+        assert_eq!(parse("USD 1,234,567.8", Locale::Point), Ok(1234567.8));
+        assert_eq!(parse("1 234,56", Locale::Comma), Ok(1234.56));
+        assert_eq!(parse("1.234,56 EUR", Locale::Comma), Ok(1234.56));
+        assert_eq!(parse("12,5€", Locale::Comma), Ok(12.5));
+
+        assert_eq!(parse("1.234,56", Locale::Auto), Ok(1234.56));
+        assert_eq!(parse("1,234.56", Locale::Auto), Ok(1234.56));
+        assert_eq!(parse("1 234,5", Locale::Auto), Ok(1234.5));
+        assert_eq!(parse("1,234,567", Locale::Auto), Ok(1234567.0));
+        assert_eq!(parse("0,125", Locale::Auto), Ok(0.125));
+        assert_eq!(
+            parse("1,234", Locale::Auto),
+            Err(ParseAmountError::Ambiguous("1,234".to_string(), ','))
+        );
+        assert!(matches!(
+            parse("€1.500", Locale::Auto),
+            Err(ParseAmountError::Ambiguous(_, '.'))
+        ));
+
+        // Misplaced thousands separators, several decimal separators and unknown symbols are invalid
+        for text in [
+            "1,23.5",
+            "12,34,567",
+            "1.234.5",
+            "1.2.3",
+            "--1",
+            "12 AB",
+            "1_000",
+            "€",
+            "",
+        ] {
+            assert_eq!(
+                parse(text, Locale::Point),
+                Err(ParseAmountError::Invalid(text.to_string())),
+                "{text}"
+            );
+        }
+    }
+}
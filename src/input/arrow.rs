@@ -0,0 +1,323 @@
+use std::{
+    fs::File,
+    io::{self, Read, Seek},
+    marker::PhantomData,
+    path::Path,
+};
+
+use arrow_array::RecordBatch;
+use arrow_ipc::reader::{FileReader, StreamReader};
+use arrow_schema::ArrowError;
+
+use crate::transaction::{columnar::Columns, Id};
+
+use super::{amount::AmountError, ReadError, Reader, SourceRow, TransactionRow};
+
+/// Magic bytes at the start of an Arrow IPC file, as opposed to an Arrow IPC stream
+const FILE_MAGIC: &[u8; 6] = b"ARROW1";
+
+/// A [`Reader`] of transactions from an Arrow IPC file or stream with the columns of a [`TransactionRow`]
+/// Record batches are read lazily, one at a time, while iterating over the transactions
+pub struct ArrowReader<C = u16, T = u32> {
+    rows: Rows<C, T>,
+}
+
+impl<C: Id, T: Id> ArrowReader<C, T> {
+    /// Open the Arrow IPC file or stream at `path`, telling them apart from their magic bytes
+    pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let mut file = File::open(path)?;
+
+        let mut magic = [0; FILE_MAGIC.len()];
+        let is_file = match file.read_exact(&mut magic) {
+            Ok(()) => &magic == FILE_MAGIC,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => false,
+            Err(e) => return Err(e.into()),
+        };
+        file.rewind()?;
+
+        let batches: Box<dyn Iterator<Item = Result<RecordBatch, ArrowError>>> = if is_file {
+            Box::new(FileReader::try_new(file, None)?)
+        } else {
+            Box::new(StreamReader::try_new(io::BufReader::new(file), None)?)
+        };
+
+        Ok(Self {
+            rows: Rows {
+                batches,
+                current: None,
+                row: 0,
+                index: 0,
+                _marker: PhantomData,
+            },
+        })
+    }
+
+    /// Convert the reader to an iterator over the records along with the [`SourceRow`] they have been read from
+    /// The line of an Arrow row is its row number across all the record batches, starting at 1
+    pub fn into_rows(self) -> Rows<C, T> {
+        self.rows
+    }
+}
+
+/// Iterator over the records of an Arrow IPC file or stream along with the [`SourceRow`] they have been read from
+pub struct Rows<C, T> {
+    batches: Box<dyn Iterator<Item = Result<RecordBatch, ArrowError>>>,
+
+    /// Columns of the record batch being read
+    current: Option<Columns>,
+
+    /// Index of the next row to read in the record batch being read
+    row: usize,
+
+    /// Number of rows read so far
+    index: u64,
+
+    _marker: PhantomData<fn() -> (C, T)>,
+}
+
+impl<C: Id, T: Id> Iterator for Rows<C, T> {
+    type Item = (SourceRow, Record<C, T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (text, record) = loop {
+            if let Some(columns) = self.current.as_ref().filter(|c| self.row < c.len()) {
+                let row = self.row;
+                self.row += 1;
+                break (columns.text(row), columns.record(row));
+            }
+
+            match self.batches.next()? {
+                Ok(batch) => match Columns::new(&batch) {
+                    Ok(columns) => {
+                        self.current = Some(columns);
+                        self.row = 0;
+                    }
+                    Err(e) => break (String::new(), Err(ReadError::read(e))),
+                },
+                Err(e) => break (String::new(), Err(ReadError::read(e))),
+            }
+        };
+
+        self.index += 1;
+        let record = record.map_err(|e| e.locate(self.index, None));
+
+        Some((SourceRow::new(self.index, None, text), record))
+    }
+}
+
+type Record<C, T> = Result<TransactionRow<C, T>, ReadError>;
+
+impl<C: Id, T: Id> Reader<C, T> for ArrowReader<C, T> {
+    type IntoError = AmountError;
+    type Item = TransactionRow<C, T>;
+    type Error = ReadError;
+
+    type Iterator = std::iter::Map<Rows<C, T>, fn((SourceRow, Record<C, T>)) -> Record<C, T>>;
+
+    fn into_iter(self) -> Self::Iterator {
+        self.rows.map(|(_, record)| record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow_array::{
+        types::Int8Type, ArrayRef, DictionaryArray, Float64Array, Int64Array, StringArray,
+        UInt16Array, UInt64Array,
+    };
+    use arrow_ipc::writer::{FileWriter, StreamWriter};
+
+    use crate::{
+        input::{Locale, Precision},
+        transaction::{
+            columnar::RecordBatchError,
+            engine::{Rejection, TransactionEngine},
+            Account, Transaction, TransactionOperation,
+        },
+    };
+
+    use super::*;
+
+    fn batch() -> RecordBatch {
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            (
+                "type",
+                Arc::new(StringArray::from(vec![
+                    "deposit",
+                    "withdrawal",
+                    "dispute",
+                    "deposit",
+                ])),
+            ),
+            ("client", Arc::new(UInt16Array::from(vec![1, 1, 1, 2]))),
+            ("tx", Arc::new(Int64Array::from(vec![1, 2, 1, 3]))),
+            (
+                "amount",
+                Arc::new(Float64Array::from(vec![Some(3.0), Some(1.0), None, None])),
+            ),
+        ];
+
+        RecordBatch::try_from_iter(columns).unwrap()
+    }
+
+    fn read(path: &Path) -> Vec<(u64, Result<Transaction, ReadError>)> {
+        ArrowReader::new(path)
+            .unwrap()
+            .into_rows()
+            .map(|(row, record)| {
+                (
+                    row.line,
+                    record.and_then(|r| r.try_into().map_err(ReadError::invalid)),
+                )
+            })
+            .collect()
+    }
+
+    fn assert_rows(rows: &[(u64, Result<Transaction, ReadError>)]) {
+        assert_eq!(
+            rows.iter().map(|r| r.0).collect::<Vec<_>>(),
+            [1, 2, 3, 4, 5, 6, 7, 8]
+        );
+        assert!(matches!(
+            rows[0].1.as_ref().map(|t| t.operation),
+            Ok(TransactionOperation::Deposit(amount)) if amount == 3.0
+        ));
+        assert!(matches!(
+            rows[2].1.as_ref().map(|t| t.operation),
+            Ok(TransactionOperation::Dispute)
+        ));
+
+        // Deposits without an amount are rejected like in CSV files
+        assert!(rows[3].1.is_err());
+        assert!(rows[7].1.is_err());
+    }
+
+    #[test]
+    fn read_file_and_stream() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("transactions.arrow");
+        let mut writer =
+            FileWriter::try_new(File::create(&path).unwrap(), &batch().schema()).unwrap();
+        writer.write(&batch()).unwrap();
+        writer.write(&batch()).unwrap();
+        writer.finish().unwrap();
+        assert_rows(&read(&path));
+
+        let path = dir.path().join("transactions.arrows");
+        let mut writer =
+            StreamWriter::try_new(File::create(&path).unwrap(), &batch().schema()).unwrap();
+        writer.write(&batch()).unwrap();
+        writer.write(&batch()).unwrap();
+        writer.finish().unwrap();
+        assert_rows(&read(&path));
+    }
+
+    #[test]
+    fn row_text() {
+        let columns = Columns::new(&batch()).unwrap();
+        assert_eq!(columns.text(1), "withdrawal,1,2,1,,");
+        assert_eq!(columns.text(2), "dispute,1,1,,,");
+
+        let batch = batch();
+        let mut columns = batch
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| field.name().clone())
+            .zip(batch.columns().iter().cloned())
+            .collect::<Vec<(String, ArrayRef)>>();
+        columns.push((
+            "batch".to_string(),
+            Arc::new(UInt64Array::from(vec![Some(7), None, None, None])),
+        ));
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+        assert_eq!(Columns::new(&batch).unwrap().text(0), "deposit,1,1,3,7,");
+    }
+
+    #[test]
+    fn process_record_batch() {
+        let mut engine = TransactionEngine::<u16, u32>::new();
+
+        // The malformed deposit of the last row aborts the whole record batch
+        let Err(RecordBatchError::InvalidRow(error)) =
+            engine.process_record_batch(&batch(), Locale::default(), Precision::default())
+        else {
+            panic!("the deposit without an amount should be rejected");
+        };
+        assert_eq!(error.position().line, Some(4));
+        assert_eq!(error.position().field.as_deref(), Some("amount"));
+        assert!(engine.account(&1.into()).is_none());
+
+        let batch = batch().slice(0, 3);
+        let results = engine
+            .process_record_batch(&batch, Locale::default(), Precision::default())
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(Result::is_ok));
+
+        let Some(Account {
+            available, held, ..
+        }) = engine.account(&1.into())
+        else {
+            panic!("client 1 should exist");
+        };
+        assert_eq!((available, held), (-1.0, 3.0));
+    }
+
+    #[test]
+    fn process_record_batch_in_batches() {
+        let kinds = DictionaryArray::<Int8Type>::from_iter([
+            "deposit",
+            "deposit",
+            "withdrawal",
+            "deposit",
+            "withdrawal",
+        ]);
+        let columns: Vec<(&str, ArrayRef)> = vec![
+            ("type", Arc::new(kinds)),
+            ("client", Arc::new(UInt64Array::from(vec![1, 2, 2, 1, 1]))),
+            ("tx", Arc::new(UInt64Array::from(vec![1, 2, 3, 4, 5]))),
+            (
+                "amount",
+                Arc::new(Float64Array::from(vec![10.0, 5.0, 8.0, 1.0, 2.0])),
+            ),
+            (
+                "batch",
+                Arc::new(UInt64Array::from(vec![
+                    None,
+                    Some(7),
+                    Some(7),
+                    Some(8),
+                    Some(8),
+                ])),
+            ),
+        ];
+        let batch = RecordBatch::try_from_iter(columns).unwrap();
+
+        let mut engine = TransactionEngine::<u64, u64>::new();
+        let results = engine
+            .process_record_batch(&batch, Locale::default(), Precision::default())
+            .unwrap();
+
+        // The overdrawn withdrawal rolls back its batch only
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1].as_ref().err(),
+            Some(&Rejection::RolledBack(7.into()))
+        );
+        assert_eq!(
+            results[2].as_ref().err(),
+            Some(&Rejection::InsufficientFunds(3.into()))
+        );
+        assert!(results[3..].iter().all(Result::is_ok));
+
+        assert!(engine.account(&2.into()).is_none());
+        assert!(engine.account(&1.into()).is_some_and(|a| a.total == 9.0));
+        assert!(engine
+            .transaction(&1.into(), &5.into())
+            .and_then(|t| t.provenance)
+            .is_some_and(|p| p.line == 5));
+    }
+}
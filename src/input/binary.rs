@@ -0,0 +1,769 @@
+//! The dab-native binary format, a compact file of fixed-width transaction records
+//!
+//! A file starts with a header of [`HEADER_SIZE`] bytes followed by records of [`RECORD_SIZE`] bytes, every integer
+//! being stored in little-endian byte order
+//!
+//! | Offset | Size | Header field                       |
+//! |--------|------|------------------------------------|
+//! | 0      | 4    | Magic bytes `DABT`                 |
+//! | 4      | 2    | Schema version, [`SCHEMA_VERSION`] |
+//! | 6      | 2    | Size of a record in bytes          |
+//! | 8      | 8    | Number of records                  |
+//! | 16     | 4    | CRC-32 checksum of the records     |
+//! | 20     | 4    | Reserved, zero                     |
+//!
+//! | Offset | Size | Record field                                                                   |
+//! |--------|------|--------------------------------------------------------------------------------|
+//! | 0      | 1    | Type, from 0 to 4 for deposits, withdrawals, disputes, resolves, chargebacks   |
+//! | 1      | 1    | Flags telling whether the amount (1), batch (2) and timestamp (4) are set      |
+//! | 2      | 6    | Reserved, zero                                                                 |
+//! | 8      | 8    | Client identifier                                                              |
+//! | 16     | 8    | Transaction identifier                                                         |
+//! | 24     | 8    | Amount as a 64-bit float                                                       |
+//! | 32     | 8    | Batch identifier                                                               |
+//! | 40     | 8    | Timestamp in seconds since the Unix epoch                                      |
+//!
+//! Identifiers are stored as 64-bit unsigned integers, so that only integer identifiers can be written
+
+use std::{
+    fmt::{self, Write as _},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+use anyhow::Context;
+use thiserror::Error;
+
+use crate::transaction::{Id, Transaction, TransactionOperation};
+
+use super::{
+    amount::{Amount, AmountError},
+    Position, ReadError, Reader, SourceRow, TransactionRow,
+};
+
+/// Magic bytes at the start of a binary file
+pub const MAGIC: &[u8; 4] = b"DABT";
+
+/// Version of the layout of the header and records, increased whenever it changes
+pub const SCHEMA_VERSION: u16 = 1;
+
+/// Size of the header in bytes
+pub const HEADER_SIZE: usize = 24;
+
+/// Size of a record in bytes
+pub const RECORD_SIZE: usize = 48;
+
+/// Names of the transaction types, indexed by their code in a record
+const TYPES: [&str; 5] = ["deposit", "withdrawal", "dispute", "resolve", "chargeback"];
+
+/// Flag set when a record has an amount
+const HAS_AMOUNT: u8 = 1;
+
+/// Flag set when a record has a batch
+const HAS_BATCH: u8 = 1 << 1;
+
+/// Flag set when a record has a timestamp
+const HAS_TIMESTAMP: u8 = 1 << 2;
+
+/// Error raised when reading or writing a binary file
+#[derive(Debug, Error)]
+pub enum BinaryError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+
+    #[error("not a dab binary file")]
+    Magic,
+
+    #[error("unsupported schema version {0}, expected {SCHEMA_VERSION}")]
+    Version(u16),
+
+    #[error("unexpected record size of {0} bytes, expected {RECORD_SIZE}")]
+    RecordSize(u16),
+
+    #[error("file is truncated after {read} of {count} records")]
+    Truncated { read: u64, count: u64 },
+
+    #[error("file has trailing data after its {0} records")]
+    TrailingData(u64),
+
+    #[error("checksum mismatch, expected {expected:#010x} but records have {actual:#010x}")]
+    Checksum { expected: u32, actual: u32 },
+
+    #[error("unknown transaction type {0}")]
+    Type(u8),
+
+    #[error("identifier `{0}` is not an integer and cannot be written to a binary file")]
+    NonInteger(String),
+
+    #[error("identifier {0} is out of range")]
+    OutOfRange(u64),
+}
+
+impl From<BinaryError> for ReadError {
+    fn from(error: BinaryError) -> Self {
+        match error {
+            BinaryError::Io(source) => source.into(),
+            error => ReadError::Parse {
+                position: Position::default(),
+                source: Box::new(error),
+            },
+        }
+    }
+}
+
+/// Header of a binary file
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    count: u64,
+    checksum: u32,
+}
+
+impl Header {
+    fn encode(&self) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0; HEADER_SIZE];
+        bytes[0..4].copy_from_slice(MAGIC);
+        bytes[4..6].copy_from_slice(&SCHEMA_VERSION.to_le_bytes());
+        bytes[6..8].copy_from_slice(&(RECORD_SIZE as u16).to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.count.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8; HEADER_SIZE]) -> Result<Self, BinaryError> {
+        if &bytes[0..4] != MAGIC {
+            return Err(BinaryError::Magic);
+        }
+
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != SCHEMA_VERSION {
+            return Err(BinaryError::Version(version));
+        }
+
+        let size = u16::from_le_bytes([bytes[6], bytes[7]]);
+        if usize::from(size) != RECORD_SIZE {
+            return Err(BinaryError::RecordSize(size));
+        }
+
+        Ok(Self {
+            count: u64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            checksum: u32::from_le_bytes(bytes[16..20].try_into().unwrap()),
+        })
+    }
+}
+
+/// A record as stored in a binary file, before its identifiers are parsed
+#[derive(Debug, Clone, Copy, Default)]
+struct Record {
+    kind: u8,
+    client: u64,
+    tx: u64,
+    amount: Option<f64>,
+    batch: Option<u64>,
+    timestamp: Option<u64>,
+}
+
+impl Record {
+    fn encode(&self) -> [u8; RECORD_SIZE] {
+        let mut flags = 0;
+        if self.amount.is_some() {
+            flags |= HAS_AMOUNT;
+        }
+        if self.batch.is_some() {
+            flags |= HAS_BATCH;
+        }
+        if self.timestamp.is_some() {
+            flags |= HAS_TIMESTAMP;
+        }
+
+        let mut bytes = [0; RECORD_SIZE];
+        bytes[0] = self.kind;
+        bytes[1] = flags;
+        bytes[8..16].copy_from_slice(&self.client.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.tx.to_le_bytes());
+        bytes[24..32].copy_from_slice(&self.amount.unwrap_or_default().to_le_bytes());
+        bytes[32..40].copy_from_slice(&self.batch.unwrap_or_default().to_le_bytes());
+        bytes[40..48].copy_from_slice(&self.timestamp.unwrap_or_default().to_le_bytes());
+        bytes
+    }
+
+    fn decode(bytes: &[u8; RECORD_SIZE]) -> Self {
+        let field =
+            |offset: usize| u64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+        let flags = bytes[1];
+
+        Self {
+            kind: bytes[0],
+            client: field(8),
+            tx: field(16),
+            amount: (flags & HAS_AMOUNT != 0).then(|| f64::from_bits(field(24))),
+            batch: (flags & HAS_BATCH != 0).then(|| field(32)),
+            timestamp: (flags & HAS_TIMESTAMP != 0).then(|| field(40)),
+        }
+    }
+
+    /// Convert the record to a [`TransactionRow`], parsing its identifiers as `C` and `T`
+    fn into_row<C: Id, T: Id>(self) -> Result<TransactionRow<C, T>, ReadError> {
+        let kind = TYPES
+            .get(usize::from(self.kind))
+            .ok_or_else(|| ReadError::field("type", BinaryError::Type(self.kind)))?;
+
+        TransactionRow::from_columns(
+            kind,
+            parse_id(self.client).map_err(|e| ReadError::field("client", e))?,
+            parse_id(self.tx).map_err(|e| ReadError::field("tx", e))?,
+            self.amount.map(Amount::Number),
+            self.batch,
+            self.timestamp,
+        )
+    }
+}
+
+impl fmt::Display for Record {
+    /// Write the record as a CSV row with the `type`, `client`, `tx`, `amount`, `batch` and `timestamp` columns
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match TYPES.get(usize::from(self.kind)) {
+            Some(kind) => write!(f, "{kind}")?,
+            None => write!(f, "{}", self.kind)?,
+        }
+        write!(f, ",{},{},", self.client, self.tx)?;
+
+        if let Some(amount) = self.amount {
+            write!(f, "{amount}")?;
+        }
+        f.write_char(',')?;
+        if let Some(batch) = self.batch {
+            write!(f, "{batch}")?;
+        }
+        f.write_char(',')?;
+        if let Some(timestamp) = self.timestamp {
+            write!(f, "{timestamp}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Maximum number of decimal digits of a 64-bit unsigned integer
+const MAX_DIGITS: usize = 20;
+
+/// Stack buffer holding the decimal representation of an identifier, so that identifiers are converted from and to
+/// integers without allocating
+#[derive(Default)]
+struct Digits {
+    buf: [u8; MAX_DIGITS],
+    len: usize,
+}
+
+impl Digits {
+    fn as_str(&self) -> &str {
+        // Only ASCII digits are written by `parse_id` and only whole strings by `format_id`
+        std::str::from_utf8(&self.buf[..self.len]).unwrap_or_default()
+    }
+}
+
+impl fmt::Write for Digits {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > MAX_DIGITS {
+            return Err(fmt::Error);
+        }
+
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Parse the identifier stored as `value` in a record as an `I`
+fn parse_id<I: Id>(value: u64) -> Result<I, BinaryError> {
+    let mut digits = Digits::default();
+    let _ = write!(digits, "{value}");
+
+    digits
+        .as_str()
+        .parse()
+        .map_err(|_| BinaryError::OutOfRange(value))
+}
+
+/// Convert an identifier to the integer stored in a record
+fn format_id(id: &impl fmt::Display) -> Result<u64, BinaryError> {
+    let mut digits = Digits::default();
+
+    write!(digits, "{id}")
+        .ok()
+        .and_then(|_| digits.as_str().parse().ok())
+        .ok_or_else(|| BinaryError::NonInteger(id.to_string()))
+}
+
+/// Table of the CRC-32 (IEEE 802.3) checksum, one entry per byte value
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Running CRC-32 checksum of the records of a file
+#[derive(Debug, Clone, Copy)]
+struct Crc32(u32);
+
+impl Crc32 {
+    fn new() -> Self {
+        Self(!0)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = CRC32_TABLE[usize::from(self.0 as u8 ^ byte)] ^ (self.0 >> 8);
+        }
+    }
+
+    fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+/// A [`Reader`] of transactions from a dab-native binary file, see the [module documentation](self) for its layout
+///
+/// The header is checked when the reader is created while records are read lazily, one at a time, while iterating
+/// over the transactions. Files opened with [`BinaryReader::new`] are read twice, their records being checked against
+/// the count and checksum of their header before the reader is returned. Otherwise, such as for the standard input,
+/// the checksum of the records is only verified once they have all been read, a mismatch being yielded as an error
+/// after the last record, so that every transaction read before must then be discarded
+pub struct BinaryReader<C = u16, T = u32, R = Box<dyn Read>> {
+    rows: Rows<C, T, R>,
+}
+
+impl<C: Id, T: Id> BinaryReader<C, T> {
+    /// Open the binary file at `path`, or the standard input if `path` is `-`.
+    /// Compressed files are transparently decompressed, see [`super::open`]. The records of a file are verified
+    /// upfront, which the standard input cannot be
+    pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if path != Path::new("-") {
+            verify(super::open(path)?)
+                .with_context(|| format!("{} is corrupted", path.display()))?;
+        }
+
+        Ok(Self::from_reader(super::open(path)?)?)
+    }
+}
+
+/// Check that the records read from `reader` match the count and checksum of its header
+fn verify(reader: impl Read) -> Result<(), BinaryError> {
+    let mut rows = BinaryReader::<u64, u64, _>::from_reader(reader)?.rows;
+    while rows.read_record()?.is_some() {}
+
+    rows.finish()
+}
+
+impl<C: Id, T: Id, R: Read> BinaryReader<C, T, R> {
+    /// Read a binary file from `reader`, checking its header
+    pub fn from_reader(reader: R) -> Result<Self, BinaryError> {
+        let mut reader = BufReader::new(reader);
+
+        let mut header = [0; HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        let header = Header::decode(&header)?;
+
+        Ok(Self {
+            rows: Rows {
+                reader,
+                header,
+                crc: Crc32::new(),
+                index: 0,
+                done: false,
+                _marker: PhantomData,
+            },
+        })
+    }
+
+    /// Number of records of the file, as written in its header
+    pub fn len(&self) -> u64 {
+        self.rows.header.count
+    }
+
+    /// Whether the file has no record
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Convert the reader to an iterator over the records along with the [`SourceRow`] they have been read from
+    /// The line of a record is its record number, starting at 1, and its text the record written as a CSV row
+    pub fn into_rows(self) -> Rows<C, T, R> {
+        self.rows
+    }
+}
+
+/// Iterator over the records of a binary file along with the [`SourceRow`] they have been read from
+pub struct Rows<C, T, R = Box<dyn Read>> {
+    reader: BufReader<R>,
+    header: Header,
+    crc: Crc32,
+
+    /// Number of records read so far
+    index: u64,
+
+    /// Whether the end of the file has been checked
+    done: bool,
+
+    _marker: PhantomData<fn() -> (C, T)>,
+}
+
+impl<C, T, R: Read> Rows<C, T, R> {
+    /// Read the bytes of the next record, updating the checksum of the records read so far
+    /// Returns [`None`] once every record of the header has been read
+    fn read_record(&mut self) -> Result<Option<[u8; RECORD_SIZE]>, BinaryError> {
+        if self.index == self.header.count {
+            return Ok(None);
+        }
+
+        let mut bytes = [0; RECORD_SIZE];
+        self.reader
+            .read_exact(&mut bytes)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => BinaryError::Truncated {
+                    read: self.index,
+                    count: self.header.count,
+                },
+                _ => e.into(),
+            })?;
+
+        self.crc.update(&bytes);
+        self.index += 1;
+        Ok(Some(bytes))
+    }
+
+    /// Check that the file ends after its last record and that the records match the checksum of the header
+    fn finish(&mut self) -> Result<(), BinaryError> {
+        if self.reader.read(&mut [0])? != 0 {
+            return Err(BinaryError::TrailingData(self.header.count));
+        }
+
+        let actual = self.crc.finish();
+        if actual != self.header.checksum {
+            return Err(BinaryError::Checksum {
+                expected: self.header.checksum,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl<C: Id, T: Id, R: Read> Iterator for Rows<C, T, R> {
+    type Item = (SourceRow, Item<C, T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let byte = HEADER_SIZE as u64 + self.index * RECORD_SIZE as u64;
+
+        let (line, error) = match self.read_record() {
+            Ok(Some(bytes)) => {
+                let record = Record::decode(&bytes);
+                let row = SourceRow::new(self.index, Some(byte), record.to_string());
+
+                let record = record.into_row().map_err(|e| e.locate(row.line, row.byte));
+                return Some((row, record));
+            }
+            // The end of the file is located after the last record
+            Ok(None) => (self.index, self.finish().err()),
+            Err(e) => (self.index + 1, Some(e)),
+        };
+
+        self.done = true;
+        let row = SourceRow::new(line, Some(byte), String::new());
+        let error = ReadError::from(error?).locate(row.line, row.byte);
+        Some((row, Err(error)))
+    }
+}
+
+type Item<C, T> = Result<TransactionRow<C, T>, ReadError>;
+
+impl<C: Id, T: Id, R: Read> Reader<C, T> for BinaryReader<C, T, R> {
+    type IntoError = AmountError;
+    type Item = TransactionRow<C, T>;
+    type Error = ReadError;
+
+    type Iterator = std::iter::Map<Rows<C, T, R>, fn((SourceRow, Item<C, T>)) -> Item<C, T>>;
+
+    fn into_iter(self) -> Self::Iterator {
+        self.rows.map(|(_, record)| record)
+    }
+}
+
+/// Writes transactions to a dab-native binary file, see the [module documentation](self) for its layout
+///
+/// The number of records and their checksum are only known once every transaction has been written, so that the
+/// header is rewritten by [`BinaryWriter::into_inner`], which must be called for the file to be valid
+pub struct BinaryWriter<W: Write + Seek> {
+    writer: BufWriter<W>,
+
+    /// Position of the header in the underlying writer
+    start: u64,
+
+    count: u64,
+    crc: Crc32,
+}
+
+impl<W: Write + Seek> BinaryWriter<W> {
+    /// Start writing a binary file to `writer`, at its current position
+    pub fn new(writer: W) -> Result<Self, BinaryError> {
+        let mut writer = BufWriter::new(writer);
+        let start = writer.stream_position()?;
+        writer.write_all(&[0; HEADER_SIZE])?;
+
+        Ok(Self {
+            writer,
+            start,
+            count: 0,
+            crc: Crc32::new(),
+        })
+    }
+
+    /// Write a `transaction` along with the `timestamp` of the row it has been read from, if any.
+    /// Fails if an identifier of the transaction is not an integer
+    pub fn write<C: Id, T: Id>(
+        &mut self,
+        transaction: &Transaction<C, T>,
+        timestamp: Option<u64>,
+    ) -> Result<(), BinaryError> {
+        let (kind, amount) = match transaction.operation {
+            TransactionOperation::Deposit(amount) => (0, Some(amount)),
+            TransactionOperation::Withdrawal(amount) => (1, Some(amount)),
+            TransactionOperation::Dispute => (2, None),
+            TransactionOperation::Resolve => (3, None),
+            TransactionOperation::Chargeback => (4, None),
+        };
+
+        let record = Record {
+            kind,
+            client: format_id(&transaction.client)?,
+            tx: format_id(&transaction.id)?,
+            amount,
+            batch: transaction.batch.as_ref().map(format_id).transpose()?,
+            timestamp,
+        };
+
+        let bytes = record.encode();
+        self.writer.write_all(&bytes)?;
+        self.crc.update(&bytes);
+        self.count += 1;
+
+        Ok(())
+    }
+
+    /// Write the header of the file and return the underlying writer, positioned after the last record
+    pub fn into_inner(mut self) -> Result<W, BinaryError> {
+        let header = Header {
+            count: self.count,
+            checksum: self.crc.finish(),
+        };
+
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.start))?;
+        self.writer.write_all(&header.encode())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+
+        self.writer.into_inner().map_err(|e| e.into_error().into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use proptest::prelude::*;
+
+    use crate::{input::CsvReader, AccountOrder, TransactionEngine};
+
+    use super::*;
+
+    /// Process the `transactions` and describe the outcome of every transaction along with the resulting accounts
+    fn run(transactions: impl IntoIterator<Item = Transaction>) -> Vec<String> {
+        let mut engine = TransactionEngine::new();
+        let mut output = transactions
+            .into_iter()
+            .map(|transaction| format!("{:?}", engine.try_process(transaction)))
+            .collect::<Vec<_>>();
+        output.extend(
+            engine
+                .accounts(AccountOrder::Client)
+                .map(|account| format!("{account:?}")),
+        );
+        output
+    }
+
+    fn row() -> impl Strategy<Value = String> {
+        // Amounts have at most four decimal places
+        let amount = (1u32..=1_000_000).prop_map(|a| a as f64 / 10_000.0);
+        let kind = prop_oneof![
+            3 => amount.clone().prop_map(|a| format!("deposit,{a}")),
+            2 => amount.prop_map(|a| format!("withdrawal,{a}")),
+            2 => Just("dispute,".to_string()),
+            1 => Just("resolve,".to_string()),
+            1 => Just("chargeback,".to_string()),
+        ];
+
+        (
+            kind,
+            1u16..=4,
+            1u32..=16,
+            prop::option::of(1u64..=3),
+            prop::option::of(0u64..1 << 40),
+        )
+            .prop_map(|(kind, client, tx, batch, timestamp)| {
+                let (kind, amount) = kind.split_once(',').unwrap();
+                let batch = batch.map(|b| b.to_string()).unwrap_or_default();
+                let timestamp = timestamp.map(|t| t.to_string()).unwrap_or_default();
+                format!("{kind},{client},{tx},{amount},{batch},{timestamp}")
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn round_trip_engine_output(rows in prop::collection::vec(row(), 0..200)) {
+            let mut csv = String::from("type,client,tx,amount,batch,timestamp\n");
+            for row in &rows {
+                writeln!(csv, "{row}").unwrap();
+            }
+
+            let records = CsvReader::<u16, u32, &[u8]>::from_reader(csv.as_bytes())
+                .unwrap()
+                .into_rows()
+                .map(|(_, record)| record.unwrap())
+                .collect::<Vec<_>>();
+
+            let mut writer = BinaryWriter::new(io::Cursor::new(Vec::new())).unwrap();
+            let mut transactions = Vec::new();
+            for record in records {
+                let timestamp = record.timestamp();
+                let transaction: Transaction = record.try_into().unwrap();
+                writer.write(&transaction, timestamp).unwrap();
+                transactions.push((transaction, timestamp));
+            }
+            let bytes = writer.into_inner().unwrap().into_inner();
+            prop_assert_eq!(bytes.len(), HEADER_SIZE + RECORD_SIZE * rows.len());
+
+            let read = BinaryReader::<u16, u32, &[u8]>::from_reader(bytes.as_slice())
+                .unwrap()
+                .into_rows()
+                .map(|(_, record)| {
+                    let record = record.unwrap();
+                    let timestamp = record.timestamp();
+                    (TryInto::<Transaction>::try_into(record).unwrap(), timestamp)
+                })
+                .collect::<Vec<_>>();
+
+            prop_assert_eq!(
+                read.iter().map(|(t, timestamp)| format!("{t:?} {timestamp:?}")).collect::<Vec<_>>(),
+                transactions.iter().map(|(t, timestamp)| format!("{t:?} {timestamp:?}")).collect::<Vec<_>>()
+            );
+            prop_assert_eq!(
+                run(read.into_iter().map(|(t, _)| t)),
+                run(transactions.into_iter().map(|(t, _)| t))
+            );
+        }
+    }
+
+    #[test]
+    fn reject_invalid_files() {
+        let mut writer = BinaryWriter::new(io::Cursor::new(Vec::new())).unwrap();
+        for tx in 1..=3u32 {
+            let deposit = Transaction::<u64, u32>::new(
+                70_000.into(),
+                tx.into(),
+                TransactionOperation::Deposit(1.5),
+            );
+            writer.write(&deposit, None).unwrap();
+        }
+        let bytes = writer.into_inner().unwrap().into_inner();
+
+        let errors = |bytes: &[u8]| {
+            BinaryReader::<u64, u32, &[u8]>::from_reader(bytes)
+                .unwrap()
+                .into_rows()
+                .filter_map(|(_, record)| record.err().map(|e| e.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert!(errors(&bytes).is_empty());
+
+        // Identifiers that do not fit the identifier type are malformed records
+        let rows = BinaryReader::<u16, u32, &[u8]>::from_reader(bytes.as_slice())
+            .unwrap()
+            .into_rows()
+            .collect::<Vec<_>>();
+        assert_eq!(rows[0].0.text(), "deposit,70000,1,1.5,,");
+        assert!(rows.iter().all(|(_, record)| record.is_err()));
+
+        // Corrupted records of a stream are reported after the last record
+        let mut corrupted = bytes.clone();
+        corrupted[HEADER_SIZE + RECORD_SIZE + 24] ^= 1;
+        let errors_after_last_record = errors(&corrupted);
+        assert_eq!(errors_after_last_record.len(), 1);
+        assert!(
+            errors_after_last_record[0].starts_with("line 3, byte 168: checksum mismatch"),
+            "{errors_after_last_record:?}"
+        );
+
+        // while corrupted files are rejected before any record is read
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dab-reject-corrupted-file.dab");
+        std::fs::write(&path, &corrupted).unwrap();
+        let error = BinaryReader::<u64, u32>::new(&path).err().unwrap();
+        assert!(matches!(
+            error.downcast_ref::<BinaryError>(),
+            Some(BinaryError::Checksum { .. })
+        ));
+
+        assert_eq!(
+            errors(&bytes[..bytes.len() - 1]),
+            ["line 3, byte 120: file is truncated after 2 of 3 records"]
+        );
+        assert_eq!(
+            errors(&[bytes.as_slice(), &[0]].concat()),
+            ["line 3, byte 168: file has trailing data after its 3 records"]
+        );
+
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert!(matches!(
+            BinaryReader::<u64, u32, &[u8]>::from_reader(version.as_slice()),
+            Err(BinaryError::Version(2))
+        ));
+        assert!(matches!(
+            BinaryReader::<u64, u32, &[u8]>::from_reader(&b"type,client,tx,amount\n..."[..]),
+            Err(BinaryError::Magic)
+        ));
+
+        // Only integer identifiers can be written
+        let deposit = Transaction::<String, u32>::new(
+            "alice".to_string().into(),
+            1.into(),
+            TransactionOperation::Deposit(1.5),
+        );
+        let mut writer = BinaryWriter::new(io::Cursor::new(Vec::new())).unwrap();
+        assert!(matches!(
+            writer.write(&deposit, None),
+            Err(BinaryError::NonInteger(id)) if id == "alice"
+        ));
+    }
+}
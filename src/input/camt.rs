@@ -0,0 +1,695 @@
+use std::{
+    io::{self, BufReader},
+    marker::PhantomData,
+    path::Path,
+    str::FromStr,
+};
+
+use quick_xml::{
+    escape::{self, EscapeError},
+    events::Event,
+    XmlVersion,
+};
+use thiserror::Error;
+
+use crate::transaction::Id;
+
+use super::{
+    amount::{Amount, AmountError},
+    FieldError, ReadError, Reader, SourceRow, TransactionRow,
+};
+
+/// Account of an entry whose identifier identifies the client of the transaction
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ClientAccount {
+    /// Account of the statement, which every entry of the statement belongs to
+    #[default]
+    Statement,
+
+    /// Account of the counterparty of the entry: the debtor account of credits and the creditor account of debits
+    Counterparty,
+}
+
+/// Error raised when parsing an unknown [`ClientAccount`]
+#[derive(Debug, Error)]
+#[error("unknown account `{0}`, expected one of `statement` or `counterparty`")]
+pub struct ParseClientAccountError(String);
+
+impl FromStr for ClientAccount {
+    type Err = ParseClientAccountError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "statement" => Ok(ClientAccount::Statement),
+            "counterparty" => Ok(ClientAccount::Counterparty),
+            _ => Err(ParseClientAccountError(s.to_string())),
+        }
+    }
+}
+
+/// How an identifier is extracted from an account identifier, such as an IBAN, or from an entry reference
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Extraction {
+    /// The whole identifier
+    #[default]
+    Whole,
+
+    /// The digits of the identifier, other characters being dropped
+    Digits,
+
+    /// The last characters of the identifier, such as the account number at the end of an IBAN
+    Last(usize),
+
+    /// The characters of the identifier from a start index, starting at 0, until an optional end index excluded
+    Range(usize, Option<usize>),
+}
+
+impl Extraction {
+    /// Extract an identifier from `text`
+    /// Returns [`None`] if nothing can be extracted
+    pub fn extract(&self, text: &str) -> Option<String> {
+        let chars = text.chars();
+        let id = match *self {
+            Extraction::Whole => text.to_string(),
+            Extraction::Digits => chars.filter(char::is_ascii_digit).collect(),
+            Extraction::Last(len) => chars.skip(text.chars().count().checked_sub(len)?).collect(),
+            Extraction::Range(start, end) => {
+                let end = end.unwrap_or(usize::MAX);
+                if start >= end || start >= text.chars().count() {
+                    return None;
+                }
+                chars.skip(start).take(end - start).collect()
+            }
+        };
+
+        (!id.is_empty()).then_some(id)
+    }
+}
+
+/// Error raised when parsing an invalid [`Extraction`]
+#[derive(Debug, Error)]
+#[error(
+    "invalid extraction `{0}`, expected one of `whole`, `digits`, `last:N` or `range:START..END`"
+)]
+pub struct ParseExtractionError(String);
+
+impl FromStr for Extraction {
+    type Err = ParseExtractionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseExtractionError(s.to_string());
+        let bound = |bound: &str| match bound {
+            "" => Ok(None),
+            bound => bound.parse().map(Some).map_err(|_| error()),
+        };
+
+        match s.split_once(':') {
+            None if s == "whole" => Ok(Extraction::Whole),
+            None if s == "digits" => Ok(Extraction::Digits),
+            Some(("last", len)) => len.parse().map(Extraction::Last).map_err(|_| error()),
+            Some(("range", range)) => {
+                let (start, end) = range.split_once("..").ok_or_else(error)?;
+                Ok(Extraction::Range(bound(start)?.unwrap_or(0), bound(end)?))
+            }
+            _ => Err(error()),
+        }
+    }
+}
+
+/// Options mapping the entries of camt.053 statements to transactions
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct CamtOptions {
+    /// Account whose identifier identifies the client
+    pub account: ClientAccount,
+
+    /// How the client identifier is extracted from the identifier of the account
+    pub client: Extraction,
+
+    /// How the transaction identifier is extracted from the reference of the entry
+    pub tx: Extraction,
+}
+
+/// A [`Reader`] of transactions from ISO 20022 camt.053 bank-to-customer statements
+///
+/// Every booked entry of the statements is read as a deposit if it is a credit (`CRDT`) or as a withdrawal if it is
+/// a debit (`DBIT`), pending entries being skipped. The client is extracted from the identifier, IBAN or other, of an
+/// account of the entry according to the [`CamtOptions`] and the transaction from the first reference of the entry
+/// among its `NtryRef`, its `AcctSvcrRef` and the `TxId` or `EndToEndId` of its transaction details. The booking date
+/// of an entry is read as the timestamp of the transaction.
+/// The statements are parsed lazily, one entry at a time, while iterating over the transactions
+pub struct CamtReader<C = u16, T = u32, R = Box<dyn io::Read>> {
+    rows: Rows<C, T, R>,
+}
+
+impl<C: Id, T: Id> CamtReader<C, T> {
+    /// Open the camt.053 file at `path`, or the standard input if `path` is `-`.
+    /// Compressed files are transparently decompressed, see [`super::open`]
+    pub fn new(path: impl AsRef<Path>, options: &CamtOptions) -> anyhow::Result<Self> {
+        Ok(Self::from_reader(super::open(path)?, options))
+    }
+}
+
+impl<C: Id, T: Id, R: io::Read> CamtReader<C, T, R> {
+    /// Read camt.053 statements from `reader`
+    pub fn from_reader(reader: R, options: &CamtOptions) -> Self {
+        let mut reader = quick_xml::Reader::from_reader(BufReader::new(reader));
+        reader.config_mut().trim_text(true);
+
+        Self {
+            rows: Rows {
+                reader,
+                buf: Vec::new(),
+                options: options.clone(),
+                path: Vec::new(),
+                text: String::new(),
+                statement: None,
+                entry: None,
+                index: 0,
+                done: false,
+                _marker: PhantomData,
+            },
+        }
+    }
+
+    /// Convert the reader to an iterator over the records along with the [`SourceRow`] they have been read from
+    /// The line of an entry is its number among the booked entries, starting at 1, and its text its credit or debit
+    /// indicator, account, reference, amount and currency and booking date separated by commas
+    pub fn into_rows(self) -> Rows<C, T, R> {
+        self.rows
+    }
+}
+
+/// Fields of a statement entry
+#[derive(Debug, Default)]
+struct Entry {
+    reference: Option<String>,
+    servicer_reference: Option<String>,
+    tx_id: Option<String>,
+    end_to_end_id: Option<String>,
+    amount: Option<String>,
+    currency: Option<String>,
+    indicator: Option<String>,
+    status: Option<String>,
+    booking_date: Option<String>,
+    debtor_account: Option<String>,
+    creditor_account: Option<String>,
+
+    /// Offset in bytes of the `Ntry` element
+    byte: u64,
+}
+
+/// Iterator over the records of camt.053 statements along with the [`SourceRow`] they have been read from
+pub struct Rows<C, T, R = Box<dyn io::Read>> {
+    reader: quick_xml::Reader<BufReader<R>>,
+    buf: Vec<u8>,
+    options: CamtOptions,
+
+    /// Local names of the elements being read, from the root element
+    path: Vec<String>,
+
+    /// Text of the element being read
+    text: String,
+
+    /// Account of the statement being read
+    statement: Option<String>,
+
+    /// Entry being read
+    entry: Option<Entry>,
+
+    /// Number of booked entries read so far
+    index: u64,
+
+    /// Whether the end of the statements or an error has been reached
+    done: bool,
+
+    _marker: PhantomData<fn() -> (C, T)>,
+}
+
+impl<C, T, R> Rows<C, T, R> {
+    /// Whether the element being read is at the end of `suffix`
+    fn at(&self, suffix: &[&str]) -> bool {
+        self.path.len() >= suffix.len()
+            && self.path[self.path.len() - suffix.len()..]
+                .iter()
+                .zip(suffix)
+                .all(|(name, expected)| name == expected)
+    }
+
+    /// Whether the element being read is the IBAN or other identifier of the `account` element of `parent`
+    fn account(&self, parent: &str, account: &str) -> bool {
+        self.at(&[parent, account, "Id", "IBAN"]) || self.at(&[parent, account, "Id", "Othr", "Id"])
+    }
+
+    /// Store the text of the element being read in the field of the entry or statement it belongs to
+    fn store(&mut self) {
+        let text = std::mem::take(&mut self.text);
+        if text.is_empty() {
+            return;
+        }
+
+        if self.account("Stmt", "Acct") {
+            self.statement = Some(text);
+            return;
+        }
+
+        let field: fn(&mut Entry) -> &mut Option<String> = if self.at(&["Ntry", "NtryRef"]) {
+            |entry| &mut entry.reference
+        } else if self.at(&["Ntry", "AcctSvcrRef"]) {
+            |entry| &mut entry.servicer_reference
+        } else if self.at(&["Ntry", "Amt"]) {
+            |entry| &mut entry.amount
+        } else if self.at(&["Ntry", "CdtDbtInd"]) {
+            |entry| &mut entry.indicator
+        } else if self.at(&["Ntry", "Sts"]) || self.at(&["Ntry", "Sts", "Cd"]) {
+            |entry| &mut entry.status
+        } else if self.at(&["Ntry", "BookgDt", "Dt"]) || self.at(&["Ntry", "BookgDt", "DtTm"]) {
+            |entry| &mut entry.booking_date
+        } else if self.at(&["TxDtls", "Refs", "TxId"]) {
+            |entry| &mut entry.tx_id
+        } else if self.at(&["TxDtls", "Refs", "EndToEndId"]) {
+            |entry| &mut entry.end_to_end_id
+        } else if self.account("RltdPties", "DbtrAcct") {
+            |entry| &mut entry.debtor_account
+        } else if self.account("RltdPties", "CdtrAcct") {
+            |entry| &mut entry.creditor_account
+        } else {
+            return;
+        };
+
+        // Entries with several transaction details keep the references and accounts of the first ones
+        if let Some(entry) = &mut self.entry {
+            field(entry).get_or_insert(text);
+        }
+    }
+}
+
+impl<C: Id, T: Id, R: io::Read> Rows<C, T, R> {
+    /// Read the next booked entry, if any
+    fn read_entry(&mut self) -> Result<Option<Entry>, quick_xml::Error> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_event_into(&mut self.buf)? {
+                Event::Start(start) => {
+                    let name = start.local_name().as_ref().to_string();
+                    self.text.clear();
+
+                    match name.as_str() {
+                        "Stmt" => self.statement = None,
+                        "Ntry" => {
+                            let end = self.reader.buffer_position();
+                            self.entry = Some(Entry {
+                                byte: end - start.len() as u64 - 2,
+                                ..Default::default()
+                            });
+                        }
+                        "Amt" => {
+                            let currency = start.try_get_attribute("Ccy")?;
+                            let currency = currency
+                                .map(|c| c.normalized_value(XmlVersion::Implicit1_0))
+                                .transpose()?;
+                            if let (Some(entry), Some(currency)) = (&mut self.entry, currency) {
+                                entry.currency = Some(currency.into_owned());
+                            }
+                        }
+                        _ => {}
+                    }
+
+                    self.path.push(name);
+                }
+                Event::Text(text) => self.text.push_str(&text.xml10_content()),
+                Event::CData(data) => self.text.push_str(&data),
+                Event::GeneralRef(reference) => match reference.resolve_char_ref()? {
+                    Some(c) => self.text.push(c),
+                    None => {
+                        let resolved =
+                            escape::resolve_predefined_entity(&reference).ok_or_else(|| {
+                                EscapeError::UnrecognizedEntity(
+                                    0..reference.len(),
+                                    reference.to_string(),
+                                )
+                            })?;
+                        self.text.push_str(resolved);
+                    }
+                },
+                Event::End(_) => {
+                    self.store();
+
+                    if self.path.pop().as_deref() == Some("Ntry") {
+                        let entry = self.entry.take().unwrap_or_default();
+                        // Only booked entries are transactions, pending ones may still change
+                        if entry
+                            .status
+                            .as_deref()
+                            .is_none_or(|status| status == "BOOK")
+                        {
+                            return Ok(Some(entry));
+                        }
+                    }
+                }
+                Event::Eof => return Ok(None),
+                _ => {}
+            }
+        }
+    }
+
+    /// Convert an `entry` to a record, telling the field at fault by the column of a [`TransactionRow`] it is read
+    /// into: `type` for the credit or debit indicator, `client` for the account, `tx` for the reference, `amount` and
+    /// `timestamp` for the booking date
+    fn record(&self, entry: &Entry) -> Result<TransactionRow<C, T>, ReadError> {
+        let missing = |field| ReadError::field(field, FieldError::Missing);
+        let invalid =
+            |field, value: &str| ReadError::field(field, FieldError::Invalid(value.into()));
+
+        let credit = match entry.indicator.as_deref() {
+            Some("CRDT") => true,
+            Some("DBIT") => false,
+            Some(indicator) => return Err(invalid("type", indicator)),
+            None => return Err(missing("type")),
+        };
+
+        let account = self
+            .account_of(entry, credit)
+            .ok_or_else(|| missing("client"))?;
+        let client = self
+            .options
+            .client
+            .extract(account)
+            .ok_or_else(|| invalid("client", account))?;
+
+        let reference = reference(entry).ok_or_else(|| missing("tx"))?;
+        let tx = self
+            .options
+            .tx
+            .extract(reference)
+            .ok_or_else(|| invalid("tx", reference))?;
+
+        let amount = entry.amount.as_deref().ok_or_else(|| missing("amount"))?;
+        let amount = amount.parse().map_err(|_| invalid("amount", amount))?;
+
+        let timestamp = entry
+            .booking_date
+            .as_deref()
+            .map(|date| timestamp(date).ok_or_else(|| invalid("timestamp", date)))
+            .transpose()?;
+
+        TransactionRow::from_columns(
+            if credit { "deposit" } else { "withdrawal" },
+            client.parse().map_err(|_| invalid("client", &client))?,
+            tx.parse().map_err(|_| invalid("tx", &tx))?,
+            Some(Amount::Number(amount)),
+            None,
+            timestamp,
+        )
+    }
+
+    /// Identifier of the account identifying the client of an `entry`
+    fn account_of<'a>(&'a self, entry: &'a Entry, credit: bool) -> Option<&'a str> {
+        match self.options.account {
+            ClientAccount::Statement => self.statement.as_deref(),
+            ClientAccount::Counterparty if credit => entry.debtor_account.as_deref(),
+            ClientAccount::Counterparty => entry.creditor_account.as_deref(),
+        }
+    }
+}
+
+/// First reference of an `entry`
+fn reference(entry: &Entry) -> Option<&str> {
+    [
+        &entry.reference,
+        &entry.servicer_reference,
+        &entry.tx_id,
+        &entry.end_to_end_id,
+    ]
+    .into_iter()
+    .find_map(Option::as_deref)
+}
+
+/// Parse an ISO 8601 date, such as `2024-03-01`, or date and time, such as `2024-03-01T10:30:00+01:00`, as seconds
+/// since the Unix epoch. Times without offset are read as UTC
+fn timestamp(text: &str) -> Option<u64> {
+    let (date, time) = text.split_once('T').unwrap_or((text, "00:00:00"));
+
+    let mut date = date.splitn(3, '-').map(str::parse::<i64>);
+    let (year, month, day) = (date.next()?.ok()?, date.next()?.ok()?, date.next()?.ok()?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let (time, offset) = match time.find(['Z', '+', '-']) {
+        Some(index) => time.split_at(index),
+        None => (time, ""),
+    };
+    let offset = match offset.split_at_checked(1) {
+        None | Some(("Z", "")) => 0,
+        Some((sign, offset)) => {
+            let (hours, minutes) = offset.split_once(':')?;
+            let offset = hours.parse::<i64>().ok()? * 3600 + minutes.parse::<i64>().ok()? * 60;
+            if sign == "-" {
+                -offset
+            } else {
+                offset
+            }
+        }
+    };
+
+    let mut time = time.splitn(3, ':');
+    let hours = time.next()?.parse::<i64>().ok()?;
+    let minutes = time.next()?.parse::<i64>().ok()?;
+    // Fractions of seconds are dropped
+    let seconds = time
+        .next()
+        .unwrap_or("0")
+        .split('.')
+        .next()?
+        .parse::<i64>()
+        .ok()?;
+
+    // Days since the Unix epoch of the proleptic Gregorian calendar date
+    let (year, month) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146_097 + day_of_era - 719_468;
+
+    let seconds = days * 86_400 + hours * 3600 + minutes * 60 + seconds - offset;
+    seconds.try_into().ok()
+}
+
+impl<C: Id, T: Id, R: io::Read> Iterator for Rows<C, T, R> {
+    type Item = (SourceRow, Record<C, T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let entry = match self.read_entry() {
+            Ok(Some(entry)) => entry,
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(e) => {
+                // Malformed XML cannot be read any further
+                self.done = true;
+                let (line, byte) = (self.index + 1, self.reader.buffer_position());
+                let row = SourceRow::new(line, Some(byte), String::new());
+                return Some((row, Err(ReadError::read(e).locate(line, Some(byte)))));
+            }
+        };
+        self.index += 1;
+
+        let credit = entry.indicator.as_deref() == Some("CRDT");
+        let amount = match (&entry.amount, &entry.currency) {
+            (Some(amount), Some(currency)) => format!("{amount} {currency}"),
+            (amount, _) => amount.clone().unwrap_or_default(),
+        };
+        let text = [
+            entry.indicator.as_deref().unwrap_or_default(),
+            self.account_of(&entry, credit).unwrap_or_default(),
+            reference(&entry).unwrap_or_default(),
+            &amount,
+            entry.booking_date.as_deref().unwrap_or_default(),
+        ]
+        .join(",");
+
+        let index = self.index;
+        let record = self
+            .record(&entry)
+            .map_err(|e| e.locate(index, Some(entry.byte)));
+
+        Some((SourceRow::new(index, Some(entry.byte), text), record))
+    }
+}
+
+type Record<C, T> = Result<TransactionRow<C, T>, ReadError>;
+
+impl<C: Id, T: Id, R: io::Read> Reader<C, T> for CamtReader<C, T, R> {
+    type IntoError = AmountError;
+    type Item = TransactionRow<C, T>;
+    type Error = ReadError;
+
+    type Iterator = std::iter::Map<Rows<C, T, R>, fn((SourceRow, Record<C, T>)) -> Record<C, T>>;
+
+    fn into_iter(self) -> Self::Iterator {
+        self.rows.map(|(_, record)| record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transaction::{Transaction, TransactionOperation};
+
+    use super::*;
+
+    const STATEMENT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document xmlns="urn:iso:std:iso:20022:tech:xsd:camt.053.001.02">
+  <BkToCstmrStmt>
+    <GrpHdr><MsgId>MSG-1</MsgId></GrpHdr>
+    <Stmt>
+      <Id>STMT-1</Id>
+      <Acct><Id><IBAN>DE89370400440532013000</IBAN></Id></Acct>
+      <Ntry>
+        <NtryRef>1001</NtryRef>
+        <Amt Ccy="EUR">250.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts>BOOK</Sts>
+        <BookgDt><Dt>2024-03-01</Dt></BookgDt>
+        <NtryDtls><TxDtls>
+          <Refs><EndToEndId>E2E-1</EndToEndId></Refs>
+          <RltdPties><DbtrAcct><Id><Othr><Id>CLIENT-0007</Id></Othr></Id></DbtrAcct></RltdPties>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">75.5</Amt>
+        <CdtDbtInd>DBIT</CdtDbtInd>
+        <Sts><Cd>BOOK</Cd></Sts>
+        <BookgDt><DtTm>2024-03-02T10:30:00+01:00</DtTm></BookgDt>
+        <NtryDtls><TxDtls>
+          <Refs><TxId>1002</TxId></Refs>
+          <RltdPties><CdtrAcct><Id><IBAN>FR7630006000011234567890189</IBAN></Id></CdtrAcct></RltdPties>
+        </TxDtls></NtryDtls>
+      </Ntry>
+      <Ntry>
+        <NtryRef>1003</NtryRef>
+        <Amt Ccy="EUR">10.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+        <Sts>PDNG</Sts>
+      </Ntry>
+      <Ntry>
+        <Amt Ccy="EUR">1.00</Amt>
+        <CdtDbtInd>CRDT</CdtDbtInd>
+      </Ntry>
+    </Stmt>
+  </BkToCstmrStmt>
+</Document>
+"#;
+
+    type Row = (SourceRow, Result<Transaction<String, u32>, ReadError>);
+
+    fn read(options: &CamtOptions) -> Vec<Row> {
+        CamtReader::<String, u32, &[u8]>::from_reader(STATEMENT.as_bytes(), options)
+            .into_rows()
+            .map(|(row, record)| {
+                (
+                    row,
+                    record.and_then(|r| r.try_into().map_err(ReadError::invalid)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn read_statement_entries() {
+        let rows = read(&CamtOptions::default());
+
+        // Pending entries are skipped
+        assert_eq!(rows.len(), 3);
+        for (row, _) in &rows {
+            assert!(STATEMENT[row.byte.unwrap() as usize..].starts_with("<Ntry>"));
+        }
+        assert_eq!(
+            rows[0].0.text(),
+            "CRDT,DE89370400440532013000,1001,250.00 EUR,2024-03-01"
+        );
+
+        let deposit = rows[0].1.as_ref().unwrap();
+        assert_eq!(deposit.client.to_string(), "DE89370400440532013000");
+        assert_eq!(deposit.id.to_string(), "1001");
+        assert!(
+            matches!(deposit.operation, TransactionOperation::Deposit(amount) if amount == 250.0)
+        );
+
+        let withdrawal = rows[1].1.as_ref().unwrap();
+        assert_eq!(withdrawal.id.to_string(), "1002");
+        assert!(
+            matches!(withdrawal.operation, TransactionOperation::Withdrawal(amount) if amount == 75.5)
+        );
+
+        let error = rows[2].1.as_ref().unwrap_err();
+        assert_eq!(error.position().line, Some(3));
+        assert_eq!(error.position().byte, rows[2].0.byte);
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "line 3, byte {}, field `tx`: missing value",
+                rows[2].0.byte.unwrap()
+            )
+        );
+
+        // Clients are extracted from the counterparty accounts
+        let options = CamtOptions {
+            account: ClientAccount::Counterparty,
+            client: "digits".parse().unwrap(),
+            tx: "last:2".parse().unwrap(),
+        };
+        let rows = read(&options);
+        let deposit = rows[0].1.as_ref().unwrap();
+        assert_eq!(
+            (deposit.client.to_string(), deposit.id.to_string()),
+            ("0007".into(), "1".into())
+        );
+        let withdrawal = rows[1].1.as_ref().unwrap();
+        assert_eq!(withdrawal.client.to_string(), "7630006000011234567890189");
+    }
+
+    #[test]
+    fn parse_extractions_and_dates() {
+        let extract =
+            |extraction: &str, text| extraction.parse::<Extraction>().unwrap().extract(text);
+        assert_eq!(extract("whole", "DE89 3704"), Some("DE89 3704".into()));
+        assert_eq!(extract("digits", "AB-12-c3"), Some("123".into()));
+        assert_eq!(
+            extract("last:4", "DE89370400440532013000"),
+            Some("3000".into())
+        );
+        assert_eq!(extract("last:40", "DE89"), None);
+        assert_eq!(
+            extract("range:4..12", "DE89370400440532013000"),
+            Some("37040044".into())
+        );
+        assert_eq!(
+            extract("range:12..", "DE89370400440532013000"),
+            Some("0532013000".into())
+        );
+        assert_eq!(extract("digits", "none"), None);
+        assert!("range:4".parse::<Extraction>().is_err());
+        assert!("first:4".parse::<Extraction>().is_err());
+
+        assert_eq!(timestamp("1970-01-01"), Some(0));
+        assert_eq!(timestamp("2024-03-01"), Some(1_709_251_200));
+        assert_eq!(timestamp("2024-03-01T10:30:00.123Z"), Some(1_709_289_000));
+        assert_eq!(timestamp("2024-03-01T10:30:00+01:00"), Some(1_709_285_400));
+        assert_eq!(timestamp("2024-03-01T10:30:00-01:30"), Some(1_709_294_400));
+        assert_eq!(timestamp("2024-13-01"), None);
+        assert_eq!(timestamp("1969-12-31"), None);
+    }
+}
@@ -1,83 +1,425 @@
-use std::{fs::File, path::Path};
+use std::{collections::HashMap, io, iter, marker::PhantomData, path::Path, sync::Arc};
 
-use anyhow::anyhow;
-use serde::{Deserialize, Serialize};
+use crate::transaction::{Account, Id};
 
-use crate::transaction::{Transaction, TransactionOperation};
+use super::{
+    amount::{Amount, AmountError},
+    ReadError, Reader, SourceRow, TransactionRow,
+};
 
-use super::Reader;
+/// Columns of a [`TransactionRow`], in the order expected in CSV data without header row
+const COLUMNS: [&str; 6] = ["type", "client", "tx", "amount", "batch", "timestamp"];
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-enum TransactionType {
-    Deposit,
-    Withdrawal,
-    Dispute,
-    Resolve,
-    Chargeback,
+/// Describes the layout of CSV data read by a [`CsvReader`]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct CsvDialect {
+    /// Byte separating the fields of a row
+    pub delimiter: u8,
+
+    /// Byte quoting fields, or [`None`] if fields are never quoted
+    pub quote: Option<u8>,
+
+    /// Whether the data starts with a header row. Without header row, the columns are expected in the
+    /// `type`, `client`, `tx`, `amount`, `batch` and `timestamp` order
+    pub has_headers: bool,
+
+    /// Name of the column of a [`TransactionRow`] by name of the column in the header row,
+    /// for columns that are named differently
+    pub columns: HashMap<String, String>,
+
+    /// Transaction type by alias used in the `type` column, such as `credit` for `deposit`
+    pub aliases: HashMap<String, String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub(super) struct CsvTransactionRecord {
-    r#type: TransactionType,
-    client: u16,
-    tx: u32,
-    amount: Option<f64>,
+impl Default for CsvDialect {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: Some(b'"'),
+            has_headers: true,
+            columns: HashMap::new(),
+            aliases: HashMap::new(),
+        }
+    }
 }
 
-impl TryInto<Transaction> for CsvTransactionRecord {
-    type Error = anyhow::Error;
+/// A [`Reader`] of transactions from CSV data read from `R`
+pub struct CsvReader<C = u16, T = u32, R = Box<dyn io::Read>> {
+    rows: Rows<C, T, R>,
+}
+
+impl<C: Id, T: Id> CsvReader<C, T> {
+    /// Open the CSV file at `path`, or the standard input if `path` is `-`, which must start with a header row.
+    /// Compressed files are transparently decompressed, see [`super::open`]
+    pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::from_reader(super::open(path)?)
+    }
+}
+
+impl<C: Id, T: Id, R: io::Read> CsvReader<C, T, R> {
+    /// Read CSV data from `reader`, which must start with a header row
+    pub fn from_reader(reader: R) -> anyhow::Result<Self> {
+        Self::with_dialect(reader, &CsvDialect::default())
+    }
 
-    fn try_into(self) -> Result<Transaction, Self::Error> {
-        let operation = match self.r#type {
-            TransactionType::Deposit => TransactionOperation::Deposit(
-                self.amount
-                    .ok_or(anyhow!("deposit transaction should have an amount"))?,
-            ),
+    /// Read CSV data laid out according to the `dialect` from `reader`
+    pub fn with_dialect(reader: R, dialect: &CsvDialect) -> anyhow::Result<Self> {
+        let mut reader = dialect_builder(dialect).from_reader(Recorder::new(reader, 1, 0));
 
-            TransactionType::Withdrawal => TransactionOperation::Withdrawal(
-                self.amount
-                    .ok_or(anyhow!("withdrawal transaction should have an amount"))?,
-            ),
+        let header = if dialect.has_headers {
+            let mut header = csv::StringRecord::new();
+            reader.read_record(&mut header)?;
 
-            TransactionType::Dispute => TransactionOperation::Dispute,
-            TransactionType::Resolve => TransactionOperation::Resolve,
-            TransactionType::Chargeback => TransactionOperation::Chargeback,
+            let end = reader.position().byte();
+            reader.get_mut().take(end);
+
+            Some(header)
+        } else {
+            None
         };
 
-        Ok(Transaction {
-            client: self.client.into(),
-            id: self.tx.into(),
-            operation,
+        Ok(Self {
+            rows: Rows::new(reader, Arc::new(Layout::new(header.as_ref(), dialect))),
         })
     }
+
+    /// Convert the reader to an iterator over the records along with the [`SourceRow`] they have been read from
+    pub fn into_rows(self) -> Rows<C, T, R> {
+        self.rows
+    }
+}
+
+/// Read [`Account`] records from a CSV file with the same columns as the [`crate::output::CsvWriter`] output
+pub(super) fn read_accounts<C: Id>(
+    path: impl AsRef<Path>,
+) -> anyhow::Result<csv::DeserializeRecordsIntoIter<Box<dyn io::Read>, Account<C>>> {
+    Ok(builder().from_reader(super::open(path)?).into_deserialize())
+}
+
+/// Columns of CSV data once its header row has been read, shared by the chunks of a
+/// [`super::ParallelCsvReader`]
+#[derive(Debug)]
+pub(super) struct Layout {
+    dialect: CsvDialect,
+
+    /// Names of the columns, with the `amount` column left unnamed so that amounts are kept as text to be parsed
+    /// according to a locale rather than inferred as numbers
+    headers: csv::StringRecord,
+
+    /// Index of the `type` column, if any
+    kind: Option<usize>,
+
+    /// Index of the `amount` column, if any
+    amount: Option<usize>,
+}
+
+impl Layout {
+    /// Layout of CSV data with the given `header` row, or without header row according to the `dialect`
+    pub(super) fn new(header: Option<&csv::StringRecord>, dialect: &CsvDialect) -> Self {
+        let headers = match header {
+            Some(header) => header
+                .iter()
+                .map(|name| dialect.columns.get(name).map_or(name, String::as_str))
+                .collect(),
+            None => csv::StringRecord::from(COLUMNS.to_vec()),
+        };
+
+        let kind = headers.iter().position(|name| name == "type");
+        let amount = headers.iter().position(|name| name == "amount");
+
+        Self {
+            dialect: dialect.clone(),
+            headers: headers
+                .iter()
+                .map(|name| if name == "amount" { "" } else { name })
+                .collect(),
+            kind,
+            amount,
+        }
+    }
+
+    /// Name of the column of a [`TransactionRow`] at `index`, if any
+    fn name(&self, index: u64) -> Option<String> {
+        let index = usize::try_from(index).ok()?;
+        match self.amount == Some(index) {
+            true => Some("amount".to_string()),
+            false => self.headers.get(index).map(str::to_string),
+        }
+    }
+}
+
+/// Read the records of a chunk of CSV data without header row, starting at `line` and at the byte offset `offset`
+pub(super) fn chunk<C: Id, T: Id>(
+    bytes: Vec<u8>,
+    line: u64,
+    offset: u64,
+    layout: &Arc<Layout>,
+) -> Rows<C, T, io::Cursor<Vec<u8>>> {
+    let reader = dialect_builder(&layout.dialect).from_reader(Recorder::new(
+        io::Cursor::new(bytes),
+        line,
+        offset,
+    ));
+
+    Rows::new(reader, Arc::clone(layout))
+}
+
+/// Builder of readers of CSV data laid out according to the `dialect`, that do not read the header row
+/// so that it can be renamed
+pub(super) fn dialect_builder(dialect: &CsvDialect) -> csv::ReaderBuilder {
+    let mut builder = builder();
+    builder
+        .delimiter(dialect.delimiter)
+        .has_headers(false)
+        .quoting(dialect.quote.is_some())
+        .quote(dialect.quote.unwrap_or(b'"'));
+
+    builder
+}
+
+fn builder() -> csv::ReaderBuilder {
+    let mut builder = csv::ReaderBuilder::new();
+    builder
+        .has_headers(true)
+        .delimiter(b',')
+        .trim(csv::Trim::All);
+
+    builder
+}
+
+/// An [`io::Read`] adapter that keeps the bytes read from the underlying reader until they are taken,
+/// used to retrieve the original text of the rows parsed by a [`csv::Reader`]
+struct Recorder<R> {
+    inner: R,
+
+    /// Bytes read but not taken yet
+    buf: Vec<u8>,
+
+    /// Byte offset of the first byte of `buf`, as counted by the CSV reader
+    offset: u64,
+
+    /// Byte offset of the first byte of the underlying reader in the whole data
+    base: u64,
+
+    /// Line of the first byte of `buf`, starting at 1
+    line: u64,
+}
+
+impl<R> Recorder<R> {
+    fn new(inner: R, line: u64, base: u64) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+            offset: 0,
+            base,
+            line,
+        }
+    }
+
+    /// Take the row made of the bytes up to the byte offset `end`, without the surrounding line terminators
+    fn take(&mut self, end: u64) -> SourceRow {
+        let len = (end.saturating_sub(self.offset) as usize).min(self.buf.len());
+        let bytes = &self.buf[..len];
+
+        // Blank lines preceding the row are skipped by the CSV reader
+        let terminators = bytes
+            .iter()
+            .take_while(|&&b| b == b'\r' || b == b'\n')
+            .count();
+        let blank = bytes[..terminators].iter().filter(|&&b| b == b'\n').count();
+        let row = SourceRow::new(
+            self.line + blank as u64,
+            Some(self.base + self.offset + terminators as u64),
+            String::from_utf8_lossy(bytes)
+                .trim_matches(['\r', '\n'])
+                .to_string(),
+        );
+
+        self.line += bytes.iter().filter(|&&b| b == b'\n').count() as u64;
+        self.buf.drain(..len);
+        self.offset += len as u64;
+
+        row
+    }
 }
 
-pub(super) struct CsvReader {
-    it: csv::DeserializeRecordsIntoIter<File, CsvTransactionRecord>,
+impl<R: io::Read> io::Read for Recorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.buf.extend_from_slice(&buf[..read]);
+
+        Ok(read)
+    }
 }
 
-impl CsvReader {
-    pub(super) fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
-        let reader = csv::ReaderBuilder::new()
-            .has_headers(true)
-            .delimiter(b',')
-            .trim(csv::Trim::All)
-            .from_path(path)?;
+/// Iterator over the records of a CSV file along with the [`SourceRow`] they have been read from
+pub struct Rows<C, T, R = Box<dyn io::Read>> {
+    reader: csv::Reader<Recorder<R>>,
+    layout: Arc<Layout>,
+    record: csv::StringRecord,
 
-        let it = reader.into_deserialize();
+    _marker: PhantomData<fn() -> (C, T)>,
+}
 
-        Ok(Self { it })
+impl<C, T, R> Rows<C, T, R> {
+    fn new(reader: csv::Reader<Recorder<R>>, layout: Arc<Layout>) -> Self {
+        Self {
+            reader,
+            layout,
+            record: csv::StringRecord::new(),
+            _marker: PhantomData,
+        }
     }
 }
-impl Reader for CsvReader {
-    type IntoError = anyhow::Error;
-    type Item = CsvTransactionRecord;
-    type Error = csv::Error;
 
-    type Iterator = csv::DeserializeRecordsIntoIter<File, CsvTransactionRecord>;
+impl<C: Id, T: Id, R: io::Read> Iterator for Rows<C, T, R> {
+    type Item = (SourceRow, Record<C, T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = match self.reader.read_record(&mut self.record) {
+            Ok(false) => return None,
+            Ok(true) => {
+                let Layout {
+                    dialect,
+                    headers,
+                    kind,
+                    amount,
+                } = &*self.layout;
+
+                if let Some(kind) = kind.filter(|_| !dialect.aliases.is_empty()) {
+                    let aliases = &dialect.aliases;
+                    if let Some(alias) = self.record.get(kind).and_then(|kind| aliases.get(kind)) {
+                        let mut record = csv::StringRecord::new();
+                        for (index, field) in self.record.iter().enumerate() {
+                            record.push_field(if index == kind { alias } else { field });
+                        }
+                        record.set_position(self.record.position().cloned());
+                        self.record = record;
+                    }
+                }
+
+                let amount = amount
+                    .and_then(|amount| self.record.get(amount))
+                    .filter(|amount| !amount.is_empty())
+                    .map(|amount| Amount::Text(amount.to_string()));
+
+                self.record
+                    .deserialize(Some(headers))
+                    .map(|row: TransactionRow<C, T>| row.with_amount(amount))
+            }
+            Err(e) => Err(e),
+        };
+
+        let end = self.reader.position().byte();
+        let row = self.reader.get_mut().take(end);
+
+        // Chunks are parsed by readers of their own, whose positions are relative to the chunk
+        let result = result.map_err(|e| {
+            ReadError::from(e)
+                .locate(row.line, row.byte)
+                .name_field(|index| self.layout.name(index))
+        });
+
+        Some((row, result))
+    }
+}
+
+type Record<C, T> = Result<TransactionRow<C, T>, ReadError>;
+
+impl<C: Id, T: Id, R: io::Read> Reader<C, T> for CsvReader<C, T, R> {
+    type IntoError = AmountError;
+    type Item = TransactionRow<C, T>;
+    type Error = ReadError;
+
+    type Iterator = iter::Map<Rows<C, T, R>, fn((SourceRow, Record<C, T>)) -> Record<C, T>>;
 
     fn into_iter(self) -> Self::Iterator {
-        self.it
+        self.rows.map(|(_, record)| record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_keep_line_and_original_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dab-rows-keep-line-and-original-text.csv");
+        std::fs::write(
+            &path,
+            "type, client, tx, amount\ndeposit, 1, 1, 1.5\r\n\nbogus, 1, 2, 1.0\nwithdrawal, 1, 3, 0.5",
+        )
+        .unwrap();
+
+        let rows = CsvReader::<u16, u32>::new(&path)
+            .unwrap()
+            .into_rows()
+            .map(|(row, record)| (row.line, row.text().into_owned(), record.is_ok()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            rows,
+            vec![
+                (2, "deposit, 1, 1, 1.5".to_string(), true),
+                (4, "bogus, 1, 2, 1.0".to_string(), false),
+                (5, "withdrawal, 1, 3, 0.5".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_dialect() {
+        use crate::transaction::{Transaction, TransactionOperation};
+
+        let operations = |dialect: &CsvDialect, data: &'static str| {
+            CsvReader::<u16, u32, &[u8]>::with_dialect(data.as_bytes(), dialect)
+                .unwrap()
+                .into_iter()
+                .map(|record| {
+                    let transaction: Transaction = record.unwrap().try_into().unwrap();
+                    transaction.operation
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let dialect = CsvDialect {
+            delimiter: b';',
+            columns: HashMap::from([
+                ("kind".to_string(), "type".to_string()),
+                ("customer".to_string(), "client".to_string()),
+            ]),
+            aliases: HashMap::from([
+                ("credit".to_string(), "deposit".to_string()),
+                ("debit".to_string(), "withdrawal".to_string()),
+            ]),
+            ..Default::default()
+        };
+        assert!(matches!(
+            operations(
+                &dialect,
+                "kind;customer;tx;amount\ncredit;1;1;2.5\ndebit;1;2;1.0\ndispute;1;1;"
+            )[..],
+            [
+                TransactionOperation::Deposit(deposit),
+                TransactionOperation::Withdrawal(withdrawal),
+                TransactionOperation::Dispute,
+            ] if deposit == 2.5 && withdrawal == 1.0
+        ));
+
+        // Without header row, columns are read in the order of a `TransactionRow`
+        let dialect = CsvDialect {
+            delimiter: b'\t',
+            has_headers: false,
+            ..Default::default()
+        };
+        assert!(matches!(
+            operations(&dialect, "deposit\t1\t1\t2.5\nwithdrawal\t1\t2\t1.0")[..],
+            [
+                TransactionOperation::Deposit(deposit),
+                TransactionOperation::Withdrawal(withdrawal),
+            ] if deposit == 2.5 && withdrawal == 1.0
+        ));
     }
 }
@@ -0,0 +1,238 @@
+use std::{error::Error, fmt, io};
+
+use thiserror::Error;
+
+use super::amount::AmountError;
+
+/// An error that can be sent across threads, as raised by a [`super::Reader`]
+pub type BoxError = Box<dyn Error + Send + Sync>;
+
+/// Position of a record, and of the field of the record at fault if known, in an input
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Position {
+    /// Line at which the record starts, starting at 1, or the record number for formats that are not line-based
+    pub line: Option<u64>,
+
+    /// Offset in bytes at which the record starts
+    pub byte: Option<u64>,
+
+    /// Name of the field at fault, such as `amount`
+    pub field: Option<String>,
+}
+
+impl Position {
+    /// Whether nothing is known about the position
+    pub fn is_unknown(&self) -> bool {
+        self.line.is_none() && self.byte.is_none() && self.field.is_none()
+    }
+
+    /// The position followed by a colon, or nothing if it is unknown
+    fn prefix(&self) -> String {
+        match self.is_unknown() {
+            true => String::new(),
+            false => format!("{self}: "),
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [
+            self.line.map(|line| format!("line {line}")),
+            self.byte.map(|byte| format!("byte {byte}")),
+            self.field.as_ref().map(|field| format!("field `{field}`")),
+        ];
+
+        f.write_str(&parts.into_iter().flatten().collect::<Vec<_>>().join(", "))
+    }
+}
+
+impl From<&csv::Position> for Position {
+    fn from(position: &csv::Position) -> Self {
+        Self {
+            line: Some(position.line()),
+            byte: Some(position.byte()),
+            field: None,
+        }
+    }
+}
+
+/// Error raised when a field of a record is missing or holds an invalid value.
+/// The field at fault is told by the [`Position`] of the [`ReadError`]
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+#[non_exhaustive]
+pub enum FieldError {
+    /// The record has no such field or the field has no value
+    #[error("missing value")]
+    Missing,
+
+    /// The value of the field cannot be parsed
+    #[error("invalid value `{0}`")]
+    Invalid(String),
+
+    /// The value of the field is not of the expected type, for formats with typed values
+    #[error("expected {expected}, found {found}")]
+    Unexpected {
+        expected: &'static str,
+        found: String,
+    },
+}
+
+/// Error raised when reading transactions, located at the [`Position`] of the record at fault.
+/// The underlying error is kept as the [`Error::source`] of the error, so that it can be downcast
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ReadError {
+    /// The input could not be read
+    #[error("{}{source}", .position.prefix())]
+    Io {
+        position: Position,
+        source: io::Error,
+    },
+
+    /// A record is malformed and could not be parsed
+    #[error("{}{source}", .position.prefix())]
+    Parse {
+        position: Position,
+        source: BoxError,
+    },
+
+    /// A record has been parsed but is not a valid transaction, such as a withdrawal without an amount
+    #[error("{}{source}", .position.prefix())]
+    Invalid {
+        position: Position,
+        source: BoxError,
+    },
+}
+
+impl ReadError {
+    /// Error raised by a [`super::Reader`] while reading a record. The position of [`csv::Error`] is kept while
+    /// errors that already are [`ReadError`] are returned as is
+    pub fn read(error: impl Into<BoxError>) -> Self {
+        let error = error.into();
+
+        let error = match error.downcast::<ReadError>() {
+            Ok(error) => return *error,
+            Err(error) => error,
+        };
+        let error = match error.downcast::<csv::Error>() {
+            Ok(error) => return (*error).into(),
+            Err(error) => error,
+        };
+
+        match error.downcast::<io::Error>() {
+            Ok(error) => ReadError::Io {
+                position: Position::default(),
+                source: *error,
+            },
+            Err(error) => ReadError::Parse {
+                position: Position::default(),
+                source: error,
+            },
+        }
+    }
+
+    /// Error raised when converting a record to a transaction. Errors of the amount of the record, see
+    /// [`AmountError`], are located in the `amount` field
+    pub fn invalid(error: impl Into<BoxError>) -> Self {
+        let source = error.into();
+        let field = source.is::<AmountError>().then(|| "amount".to_string());
+
+        ReadError::Invalid {
+            position: Position {
+                field,
+                ..Default::default()
+            },
+            source,
+        }
+    }
+
+    /// Error raised when the `field` of a record cannot be parsed
+    pub(crate) fn field(field: &str, source: impl Into<BoxError>) -> Self {
+        ReadError::Parse {
+            position: Position {
+                field: Some(field.to_string()),
+                ..Default::default()
+            },
+            source: source.into(),
+        }
+    }
+
+    /// Position of the record at fault
+    pub fn position(&self) -> &Position {
+        match self {
+            ReadError::Io { position, .. }
+            | ReadError::Parse { position, .. }
+            | ReadError::Invalid { position, .. } => position,
+        }
+    }
+
+    fn position_mut(&mut self) -> &mut Position {
+        match self {
+            ReadError::Io { position, .. }
+            | ReadError::Parse { position, .. }
+            | ReadError::Invalid { position, .. } => position,
+        }
+    }
+
+    /// Locate the error at `line` if its line is not known yet
+    pub fn at_line(mut self, line: u64) -> Self {
+        self.position_mut().line.get_or_insert(line);
+        self
+    }
+
+    /// Locate the error at the record starting at `line` and `byte`, whatever the position it has been raised at
+    pub(super) fn locate(mut self, line: u64, byte: Option<u64>) -> Self {
+        let position = self.position_mut();
+        position.line = Some(line);
+        position.byte = byte;
+        self
+    }
+
+    /// Name the field at fault if it is not known yet, from the index of the field in the record
+    pub(super) fn name_field(mut self, name: impl FnOnce(u64) -> Option<String>) -> Self {
+        let index = match &self {
+            ReadError::Parse { source, .. } => {
+                match source.downcast_ref::<csv::Error>().map(csv::Error::kind) {
+                    Some(csv::ErrorKind::Deserialize { err, .. }) => err.field(),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        let position = self.position_mut();
+        if position.field.is_none() {
+            position.field = index.and_then(name);
+        }
+        self
+    }
+}
+
+impl From<csv::Error> for ReadError {
+    fn from(error: csv::Error) -> Self {
+        let position = error.position().map(Position::from).unwrap_or_default();
+
+        if error.is_io_error() {
+            match error.into_kind() {
+                csv::ErrorKind::Io(source) => return ReadError::Io { position, source },
+                _ => unreachable!("I/O errors have an I/O kind"),
+            }
+        }
+
+        ReadError::Parse {
+            position,
+            source: Box::new(error),
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(source: io::Error) -> Self {
+        ReadError::Io {
+            position: Position::default(),
+            source,
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    io::{self, BufRead, BufReader},
+    marker::PhantomData,
+    path::Path,
+};
+
+use crate::transaction::Id;
+
+use super::{AmountError, ReadError, Reader, SourceRow, TransactionRow};
+
+/// A [`Reader`] of transactions from a JSON Lines file or stream, where every non-blank line is a JSON object with
+/// the fields of a [`TransactionRow`]. Other fields are ignored.
+/// Lines are read lazily, one at a time, while iterating over the transactions
+pub struct JsonLinesReader<C = u16, T = u32, R = Box<dyn io::Read>> {
+    rows: Rows<C, T, R>,
+}
+
+impl<C: Id, T: Id> JsonLinesReader<C, T> {
+    /// Open the JSON Lines file at `path`, or the standard input if `path` is `-`.
+    /// Compressed files are transparently decompressed, see [`super::open`]
+    pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(Self::from_reader(super::open(path)?))
+    }
+}
+
+impl<C: Id, T: Id, R: io::Read> JsonLinesReader<C, T, R> {
+    /// Read JSON Lines from `reader`
+    pub fn from_reader(reader: R) -> Self {
+        Self {
+            rows: Rows {
+                reader: BufReader::new(reader),
+                line: 0,
+                byte: 0,
+                _marker: PhantomData,
+            },
+        }
+    }
+
+    /// Convert the reader to an iterator over the records along with the [`SourceRow`] they have been read from
+    pub fn into_rows(self) -> Rows<C, T, R> {
+        self.rows
+    }
+}
+
+/// Iterator over the records of a JSON Lines file along with the [`SourceRow`] they have been read from
+pub struct Rows<C, T, R = Box<dyn io::Read>> {
+    reader: BufReader<R>,
+
+    /// Number of lines read so far
+    line: u64,
+
+    /// Number of bytes read so far
+    byte: u64,
+
+    _marker: PhantomData<fn() -> (C, T)>,
+}
+
+impl<C: Id, T: Id, R: io::Read> Iterator for Rows<C, T, R> {
+    type Item = (SourceRow, Record<C, T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let mut bytes = Vec::new();
+            let byte = self.byte;
+            let text = match self.reader.read_until(b'\n', &mut bytes) {
+                Ok(0) => return None,
+                Ok(read) => {
+                    self.byte += read as u64;
+                    let text = String::from_utf8(bytes)
+                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.utf8_error()));
+                    text.map(|text| text.trim_end_matches(['\r', '\n']).to_string())
+                }
+                Err(e) => Err(e),
+            };
+            self.line += 1;
+
+            let (text, record) = match text {
+                // Blank lines are skipped
+                Ok(text) if text.trim().is_empty() => continue,
+                Ok(text) => {
+                    let record = serde_json::from_str(&text);
+                    (text, record.map_err(ReadError::read))
+                }
+                Err(e) => (String::new(), Err(e.into())),
+            };
+
+            let line = self.line;
+            let record = record.map_err(|e| e.locate(line, Some(byte)));
+
+            let row = SourceRow::new(line, Some(byte), text);
+            return Some((row, record));
+        }
+    }
+}
+
+type Record<C, T> = Result<TransactionRow<C, T>, ReadError>;
+
+impl<C: Id, T: Id, R: io::Read> Reader<C, T> for JsonLinesReader<C, T, R> {
+    type IntoError = AmountError;
+    type Item = TransactionRow<C, T>;
+    type Error = ReadError;
+
+    type Iterator = std::iter::Map<Rows<C, T, R>, fn((SourceRow, Record<C, T>)) -> Record<C, T>>;
+
+    fn into_iter(self) -> Self::Iterator {
+        self.rows.map(|(_, record)| record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transaction::{Transaction, TransactionOperation};
+
+    use super::*;
+
+    #[test]
+    fn read_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dab-read-lines.jsonl");
+        std::fs::write(
+            &path,
+            concat!(
+                r#"{"type": "deposit", "client": 1, "tx": 1, "amount": 1.5, "note": "salary"}"#,
+                "\n\n",
+                r#"{"type": "dispute", "client": 1, "tx": 1}"#,
+                "\n",
+                r#"{"type": "deposit", "client": 1, "tx": 2"#,
+                "\n",
+                r#"{"type": "withdrawal", "client": 1, "tx": 3, "amount": null}"#,
+                "\n",
+            ),
+        )
+        .unwrap();
+
+        let rows = JsonLinesReader::<u16, u32>::new(&path)
+            .unwrap()
+            .into_rows()
+            .map(|(row, record)| {
+                (
+                    row.line,
+                    record.and_then(|r| r.try_into().map_err(ReadError::invalid)),
+                )
+            })
+            .collect::<Vec<(u64, Result<Transaction, ReadError>)>>();
+
+        assert_eq!(rows.iter().map(|r| r.0).collect::<Vec<_>>(), [1, 3, 4, 5]);
+        assert!(matches!(
+            rows[0].1.as_ref().map(|t| t.operation),
+            Ok(TransactionOperation::Deposit(amount)) if amount == 1.5
+        ));
+        assert!(matches!(
+            rows[1].1.as_ref().map(|t| t.operation),
+            Ok(TransactionOperation::Dispute)
+        ));
+
+        // Errors report the line of the malformed object
+        let error = rows[2].1.as_ref().unwrap_err().to_string();
+        assert!(error.starts_with("line 4, byte 118:"), "{error}");
+
+        // Withdrawals without an amount are rejected like in CSV files
+        assert!(rows[3].1.is_err());
+    }
+
+    #[test]
+    fn read_located_errors() {
+        let lines = concat!(
+            r#"{"type": "deposit", "client": 1, "tx": 1, "amount": 1.5}"#,
+            "\n",
+            r#"{"type": "deposit", "client": 1, "#,
+            "\n",
+        );
+
+        let results = super::super::read(JsonLinesReader::<u16, u32, &[u8]>::from_reader(
+            lines.as_bytes(),
+        ))
+        .collect::<Vec<_>>();
+        assert!(results[0].is_ok());
+
+        let error = results[1].as_ref().unwrap_err();
+        assert!(matches!(error, ReadError::Parse { .. }), "{error:?}");
+        assert_eq!(error.position().line, Some(2));
+        assert_eq!(error.position().byte, Some(57));
+
+        let source = std::error::Error::source(error).unwrap();
+        assert!(source.downcast_ref::<serde_json::Error>().is_some());
+    }
+}
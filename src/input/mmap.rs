@@ -0,0 +1,447 @@
+use std::{
+    collections::HashMap, fs::File, marker::PhantomData, path::Path, str, str::FromStr, sync::Arc,
+};
+
+use anyhow::{anyhow, bail};
+use csv_core::ReadRecordResult;
+use memmap2::Mmap;
+
+use crate::transaction::Id;
+
+use super::{
+    amount::{Amount, AmountError},
+    CsvDialect, FieldError, ReadError, Reader, SourceRow, TransactionRow,
+};
+
+/// Powers of ten that are exactly represented as [`f64`]
+const POWERS_OF_TEN: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
+/// A [`Reader`] of transactions from a memory-mapped CSV file
+///
+/// Records are parsed in place from the mapped file into field buffers that are reused from one record to the next,
+/// and amounts are parsed without going through a string, so that reading transactions through [`Reader::into_iter`]
+/// does not allocate per record. Rows read through [`MmapCsvReader::into_rows`] keep their text as a range of the
+/// mapped file, only decoded for the rows whose text is needed. Amounts are expected to be plain numbers, see
+/// [`super::Locale::Plain`]
+pub struct MmapCsvReader<C = u16, T = u32> {
+    parser: Parser<C, T>,
+}
+
+impl<C: Id, T: Id> MmapCsvReader<C, T> {
+    /// Map the CSV file at `path` in memory, which must not be compressed
+    ///
+    /// The file must not be modified while it is being read, which would make the records read undefined
+    pub fn new(path: impl AsRef<Path>, dialect: &CsvDialect) -> anyhow::Result<Self> {
+        let file = File::open(path)?;
+
+        // SAFETY: the mapping is only read, and the file is not expected to be modified while being read
+        let data = Arc::new(unsafe { Mmap::map(&file)? });
+        if data.starts_with(super::GZIP_MAGIC) || data.starts_with(super::ZSTD_MAGIC) {
+            bail!("compressed files cannot be memory-mapped");
+        }
+
+        let reader = csv_core::ReaderBuilder::new()
+            .delimiter(dialect.delimiter)
+            .quoting(dialect.quote.is_some())
+            .quote(dialect.quote.unwrap_or(b'"'))
+            .build();
+
+        let mut parser = Parser {
+            data,
+            offset: 0,
+            reader,
+            fields: vec![0; 1024],
+            ends: vec![0; 16],
+            len: 0,
+            columns: Columns::default(),
+            aliases: dialect.aliases.clone(),
+            _marker: PhantomData,
+        };
+
+        if dialect.has_headers && parser.read() {
+            let names = (0..parser.len)
+                .map(|index| {
+                    let name = String::from_utf8_lossy(parser.field(index).unwrap_or_default());
+                    dialect
+                        .columns
+                        .get(name.as_ref())
+                        .cloned()
+                        .unwrap_or(name.into_owned())
+                })
+                .collect::<Vec<_>>();
+            parser.columns = Columns::new(&names)?;
+        }
+
+        Ok(Self { parser })
+    }
+
+    /// Convert the reader to an iterator over the records along with the [`SourceRow`] they have been read from
+    pub fn into_rows(self) -> Rows<C, T> {
+        Rows {
+            parser: self.parser,
+            line: 1,
+            offset: 0,
+        }
+    }
+}
+
+/// Indices of the columns of a [`TransactionRow`]
+#[derive(Debug)]
+struct Columns {
+    kind: usize,
+    client: usize,
+    tx: usize,
+    amount: Option<usize>,
+    batch: Option<usize>,
+    timestamp: Option<usize>,
+}
+
+impl Default for Columns {
+    /// Columns of CSV data without header row, see [`CsvDialect::has_headers`]
+    fn default() -> Self {
+        Self {
+            kind: 0,
+            client: 1,
+            tx: 2,
+            amount: Some(3),
+            batch: Some(4),
+            timestamp: Some(5),
+        }
+    }
+}
+
+impl Columns {
+    fn new(names: &[String]) -> anyhow::Result<Self> {
+        let index = |name: &str| names.iter().position(|n| n == name);
+        let required = |name: &str| index(name).ok_or(anyhow!("missing column `{name}`"));
+
+        Ok(Self {
+            kind: required("type")?,
+            client: required("client")?,
+            tx: required("tx")?,
+            amount: index("amount"),
+            batch: index("batch"),
+            timestamp: index("timestamp"),
+        })
+    }
+}
+
+/// Parses the records of a mapped file one at a time
+struct Parser<C, T> {
+    /// Mapped file, shared with the rows read from it so that their text is only decoded when needed
+    data: Arc<Mmap>,
+
+    /// Byte offset of the next record in `data`
+    offset: usize,
+
+    reader: csv_core::Reader,
+
+    /// Fields of the last record, one after the other
+    fields: Vec<u8>,
+
+    /// End of every field of the last record in `fields`
+    ends: Vec<usize>,
+
+    /// Number of fields of the last record
+    len: usize,
+
+    columns: Columns,
+    aliases: HashMap<String, String>,
+
+    _marker: PhantomData<fn() -> (C, T)>,
+}
+
+impl<C: Id, T: Id> Parser<C, T> {
+    /// Read the next record, returning `false` at the end of the file
+    fn read(&mut self) -> bool {
+        let (mut output, mut len) = (0, 0);
+
+        loop {
+            let (result, read, written, ended) = self.reader.read_record(
+                &self.data[self.offset..],
+                &mut self.fields[output..],
+                &mut self.ends[len..],
+            );
+            self.offset += read;
+            output += written;
+            len += ended;
+
+            match result {
+                // The end of the mapped data signals the end of the file once it has been reached
+                ReadRecordResult::InputEmpty => {}
+                ReadRecordResult::OutputFull => self.fields.resize(self.fields.len() * 2, 0),
+                ReadRecordResult::OutputEndsFull => self.ends.resize(self.ends.len() * 2, 0),
+                ReadRecordResult::Record => {
+                    self.len = len;
+                    return true;
+                }
+                ReadRecordResult::End => return false,
+            }
+        }
+    }
+
+    /// Field at `index` of the last record, without surrounding whitespace
+    fn field(&self, index: usize) -> Option<&[u8]> {
+        if index >= self.len {
+            return None;
+        }
+
+        let start = if index == 0 { 0 } else { self.ends[index - 1] };
+        Some(self.fields[start..self.ends[index]].trim_ascii())
+    }
+
+    /// Convert the last record to a [`TransactionRow`]
+    fn record(&self) -> Result<TransactionRow<C, T>, ReadError> {
+        let Columns {
+            kind,
+            client,
+            tx,
+            amount,
+            batch,
+            timestamp,
+        } = self.columns;
+
+        let optional = |index: Option<usize>| {
+            index
+                .and_then(|index| self.field(index))
+                .filter(|field| !field.is_empty())
+        };
+        let required = |index: usize, name: &str| {
+            optional(Some(index)).ok_or_else(|| ReadError::field(name, FieldError::Missing))
+        };
+
+        let kind = required(kind, "type")?;
+        let kind = str::from_utf8(kind).map_err(|e| ReadError::field("type", e))?;
+        let kind = self.aliases.get(kind).map_or(kind, String::as_str);
+
+        TransactionRow::from_columns(
+            kind,
+            parse(required(client, "client")?, "client")?,
+            parse(required(tx, "tx")?, "tx")?,
+            optional(amount).map(|field| match number(field) {
+                Some(amount) => Amount::Number(amount),
+                None => Amount::Text(String::from_utf8_lossy(field).into_owned()),
+            }),
+            optional(batch)
+                .map(|field| parse(field, "batch"))
+                .transpose()?,
+            optional(timestamp)
+                .map(|field| parse(field, "timestamp"))
+                .transpose()?,
+        )
+    }
+}
+
+/// Parse the value of the column `name`
+fn parse<I: FromStr>(field: &[u8], name: &str) -> Result<I, ReadError> {
+    str::from_utf8(field)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| {
+            let value = String::from_utf8_lossy(field).into_owned();
+            ReadError::field(name, FieldError::Invalid(value))
+        })
+}
+
+/// Parse a plain decimal number such as `-1234.5678` without going through a string
+/// Returns [`None`] for other numbers, such as numbers with an exponent or too many digits to be parsed exactly,
+/// which are left to the standard parser
+fn number(field: &[u8]) -> Option<f64> {
+    let (negative, digits) = match field {
+        [b'-', digits @ ..] => (true, digits),
+        [b'+', digits @ ..] => (false, digits),
+        _ => (false, field),
+    };
+
+    let mut mantissa = 0u64;
+    let mut decimals = None;
+    for &byte in digits {
+        match byte {
+            b'0'..=b'9' => {
+                mantissa = mantissa
+                    .checked_mul(10)?
+                    .checked_add(u64::from(byte - b'0'))?;
+                decimals = decimals.map(|decimals| decimals + 1);
+            }
+            b'.' if decimals.is_none() => decimals = Some(0),
+            _ => return None,
+        }
+    }
+
+    let decimals = decimals.unwrap_or(0);
+    if digits.is_empty() || digits == b"." || mantissa >= 1 << f64::MANTISSA_DIGITS {
+        return None;
+    }
+
+    // Both the mantissa and the power of ten are exact, so that their quotient is correctly rounded
+    let amount = mantissa as f64 / POWERS_OF_TEN.get(decimals)?;
+    Some(if negative { -amount } else { amount })
+}
+
+/// Iterator over the records of a memory-mapped CSV file along with the [`SourceRow`] they have been read from
+pub struct Rows<C, T> {
+    parser: Parser<C, T>,
+
+    /// Line of the byte at `offset`, starting at 1
+    line: u64,
+
+    /// Byte offset of the end of the last record
+    offset: usize,
+}
+
+impl<C: Id, T: Id> Iterator for Rows<C, T> {
+    type Item = (SourceRow, Result<TransactionRow<C, T>, ReadError>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // The header row has been read before the first record
+        if self.offset == 0 {
+            self.line += count_lines(&self.parser.data[..self.parser.offset]);
+            self.offset = self.parser.offset;
+        }
+
+        if !self.parser.read() {
+            return None;
+        }
+
+        let start = self.offset;
+        let bytes = &self.parser.data[start..self.parser.offset];
+        self.offset = self.parser.offset;
+
+        // Blank lines preceding the row are skipped
+        let terminators = bytes
+            .iter()
+            .take_while(|&&b| b == b'\r' || b == b'\n')
+            .count();
+        let blank = bytes[..terminators].iter().filter(|&&b| b == b'\n').count();
+        let trailing = bytes[terminators..]
+            .iter()
+            .rev()
+            .take_while(|&&b| b == b'\r' || b == b'\n')
+            .count();
+        let row = SourceRow::in_data(
+            self.line + blank as u64,
+            Some((start + terminators) as u64),
+            self.parser.data.clone(),
+            start + terminators..self.parser.offset - trailing,
+        );
+        self.line += count_lines(bytes);
+
+        let record = self
+            .parser
+            .record()
+            .map_err(|e| e.locate(row.line, row.byte));
+        Some((row, record))
+    }
+}
+
+fn count_lines(bytes: &[u8]) -> u64 {
+    bytes.iter().filter(|&&b| b == b'\n').count() as u64
+}
+
+/// Iterator over the records of a memory-mapped CSV file
+pub struct Records<C, T> {
+    parser: Parser<C, T>,
+}
+
+impl<C: Id, T: Id> Iterator for Records<C, T> {
+    type Item = Result<TransactionRow<C, T>, ReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.parser.read().then(|| self.parser.record())
+    }
+}
+
+impl<C: Id, T: Id> Reader<C, T> for MmapCsvReader<C, T> {
+    type IntoError = AmountError;
+    type Item = TransactionRow<C, T>;
+    type Error = ReadError;
+
+    type Iterator = Records<C, T>;
+
+    fn into_iter(self) -> Self::Iterator {
+        Records {
+            parser: self.parser,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transaction::Transaction;
+
+    use super::*;
+
+    #[test]
+    fn parse_numbers() {
+        for text in [
+            "0",
+            "1.5",
+            "-2.25",
+            "+3",
+            "1234.5678",
+            ".5",
+            "7.",
+            "0.1",
+            "9007199254740991",
+        ] {
+            assert_eq!(number(text.as_bytes()), text.parse().ok(), "{text}");
+        }
+
+        for text in [
+            "",
+            ".",
+            "-",
+            "1e3",
+            "NaN",
+            "1.2.3",
+            "1,5",
+            "9007199254740993",
+        ] {
+            assert_eq!(number(text.as_bytes()), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn read_like_csv_reader() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dab-read-like-csv-reader.csv");
+        std::fs::write(
+            &path,
+            "type, client, tx, amount\n\ndeposit, 1, 1, 1.5\r\nbogus, 1, 2, 1.0\n\"withdrawal\", 1, 3, 5e-1\ndeposit, 1, 4, 0.3",
+        )
+        .unwrap();
+
+        let mmap = MmapCsvReader::<u16, u32>::new(&path, &CsvDialect::default())
+            .unwrap()
+            .into_rows()
+            .map(|(row, record)| {
+                (
+                    row,
+                    format!(
+                        "{:?}",
+                        record
+                            .ok()
+                            .and_then(|r| TryInto::<Transaction>::try_into(r).ok())
+                    ),
+                )
+            })
+            .collect::<Vec<_>>();
+        let csv = super::super::read_csv_rows::<u16, u32>(&path)
+            .unwrap()
+            .map(|(row, transaction)| {
+                // Only the rows tell where transactions have been read from
+                let transaction = transaction.ok().map(|t| Transaction {
+                    provenance: None,
+                    ..t
+                });
+                (row, format!("{transaction:?}"))
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(mmap.len(), 4);
+        assert_eq!(mmap, csv);
+    }
+}
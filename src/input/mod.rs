@@ -1,21 +1,66 @@
-use std::path::Path;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    fmt,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
 
-use anyhow::anyhow;
+use anyhow::Context;
+use thiserror::Error;
 
-use crate::transaction::Transaction;
+use crate::transaction::{Account, Id, Provenance, Transaction};
 
-mod csv;
+pub mod amount;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod binary;
+#[cfg(feature = "camt")]
+pub mod camt;
+pub mod csv;
+mod error;
+pub mod json;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod parallel;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+mod record;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
 
-/// An abstraction to read transaction records
-pub trait Reader {
+pub use amount::{Amount, AmountError, Locale, Precision};
+#[cfg(feature = "arrow")]
+pub use arrow::ArrowReader;
+pub use binary::{BinaryReader, BinaryWriter};
+#[cfg(feature = "camt")]
+pub use camt::{CamtOptions, CamtReader};
+pub use csv::{CsvDialect, CsvReader};
+pub use error::{BoxError, FieldError, Position, ReadError};
+pub use json::JsonLinesReader;
+#[cfg(feature = "mmap")]
+pub use mmap::MmapCsvReader;
+pub use parallel::ParallelCsvReader;
+#[cfg(feature = "parquet")]
+pub use parquet::ParquetReader;
+pub use record::TransactionRow;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteReader;
+
+/// An abstraction to read transaction records with client identifiers represented as `C`
+/// and transaction identifiers represented as `T`
+pub trait Reader<C: Id = u16, T: Id = u32> {
     /// Error raised when attempting to convert a record yielded by the reader to a [`Transaction`]
-    type IntoError: Into<Box<dyn std::error::Error>>;
+    type IntoError: Into<BoxError>;
 
     /// Type that the reader will yield that must be convertible to a [`Transaction`]
-    type Item: TryInto<Transaction, Error = Self::IntoError>;
+    type Item: TryInto<Transaction<C, T>, Error = Self::IntoError>;
 
     /// Error raised by the reader
-    type Error: Into<Box<dyn std::error::Error>>;
+    type Error: Into<BoxError>;
 
     /// An iterator type that can be used to iterate over the [`Self::Item`] elements from the reader
     /// The iterator will yield a [`Result`] over the elements
@@ -25,19 +70,801 @@ pub trait Reader {
     fn into_iter(self) -> Self::Iterator;
 }
 
+/// Location and original text of a row read from an input
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SourceRow {
+    /// Line at which the row starts, starting at 1, or the row number for formats that are not line-based
+    pub line: u64,
+
+    /// Offset in bytes at which the row starts, for formats that tell it
+    pub byte: Option<u64>,
+
+    /// Original text of the row, see [`SourceRow::text`]
+    text: RowText,
+}
+
+impl SourceRow {
+    /// Row starting at `line` and `byte` of an input, with its original `text`
+    pub fn new(line: u64, byte: Option<u64>, text: String) -> Self {
+        Self {
+            line,
+            byte,
+            text: RowText::Owned(text),
+        }
+    }
+
+    /// Row starting at `line` and `byte` of an input whose original text is the `range` of the `data` it has been
+    /// read from. The text is only decoded when needed, such as for the rows reported as rejected
+    #[cfg(feature = "mmap")]
+    pub(crate) fn in_data(
+        line: u64,
+        byte: Option<u64>,
+        data: Arc<dyn AsRef<[u8]> + Send + Sync>,
+        range: std::ops::Range<usize>,
+    ) -> Self {
+        Self {
+            line,
+            byte,
+            text: RowText::Data { data, range },
+        }
+    }
+
+    /// Original text of the row. Bytes that are not valid UTF-8 are replaced with `U+FFFD`
+    pub fn text(&self) -> Cow<'_, str> {
+        self.text.get()
+    }
+
+    /// [`Provenance`] of a transaction read from the row of the input `file`
+    pub fn provenance(&self, file: Option<Arc<str>>) -> Provenance {
+        Provenance::new(file, self.line, self.byte)
+    }
+}
+
+/// Original text of a row, either copied when the row is read or kept as a range of the data it has been read from
+#[derive(Clone)]
+enum RowText {
+    Owned(String),
+    #[cfg(feature = "mmap")]
+    Data {
+        data: Arc<dyn AsRef<[u8]> + Send + Sync>,
+        range: std::ops::Range<usize>,
+    },
+}
+
+impl RowText {
+    fn get(&self) -> Cow<'_, str> {
+        match self {
+            RowText::Owned(text) => Cow::Borrowed(text),
+            #[cfg(feature = "mmap")]
+            RowText::Data { data, range } => {
+                String::from_utf8_lossy(&(**data).as_ref()[range.clone()])
+            }
+        }
+    }
+}
+
+impl Default for RowText {
+    fn default() -> Self {
+        RowText::Owned(String::new())
+    }
+}
+
+impl PartialEq for RowText {
+    fn eq(&self, other: &Self) -> bool {
+        self.get() == other.get()
+    }
+}
+
+impl Eq for RowText {}
+
+impl fmt::Debug for RowText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+/// Magic bytes at the start of gzip compressed data
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Magic bytes at the start of zstd compressed data
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Open the file at `path` for reading, or the standard input if `path` is `-`.
+/// Data compressed with gzip or zstd is transparently decompressed, see [`decompress`]
+pub fn open(path: impl AsRef<Path>) -> io::Result<Box<dyn Read>> {
+    let path = path.as_ref();
+
+    if path == Path::new("-") {
+        decompress(io::stdin().lock())
+    } else {
+        decompress(File::open(path)?)
+    }
+}
+
+/// Wrap `reader` in a decoder if the data it holds is compressed with gzip or zstd, telling them apart from
+/// their magic bytes. Uncompressed data is read as is
+pub fn decompress(mut reader: impl Read + 'static) -> io::Result<Box<dyn Read>> {
+    // Pipes and sockets may return fewer bytes than asked for, so read until the longest magic bytes are known
+    let mut magic = Vec::with_capacity(ZSTD_MAGIC.len());
+    while magic.len() < ZSTD_MAGIC.len() {
+        let mut buffer = [0; ZSTD_MAGIC.len()];
+        match reader.read(&mut buffer[..ZSTD_MAGIC.len() - magic.len()]) {
+            Ok(0) => break,
+            Ok(read) => magic.extend_from_slice(&buffer[..read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    let compression = [GZIP_MAGIC, ZSTD_MAGIC]
+        .into_iter()
+        .find(|compression| magic.starts_with(compression));
+    let reader = BufReader::new(io::Cursor::new(magic).chain(reader));
+
+    if compression == Some(GZIP_MAGIC) {
+        #[cfg(feature = "compression")]
+        return Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)));
+        #[cfg(not(feature = "compression"))]
+        return Err(unsupported("gzip"));
+    }
+
+    if compression == Some(ZSTD_MAGIC) {
+        #[cfg(feature = "compression")]
+        return Ok(Box::new(zstd::Decoder::with_buffer(reader)?));
+        #[cfg(not(feature = "compression"))]
+        return Err(unsupported("zstd"));
+    }
+
+    Ok(Box::new(reader))
+}
+
+#[cfg(not(feature = "compression"))]
+fn unsupported(compression: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("reading {compression} compressed data requires the `compression` feature"),
+    )
+}
+
+/// Format of a file of transactions
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Format {
+    /// CSV file with a header row
+    #[default]
+    Csv,
+
+    /// JSON Lines file with one JSON object per line
+    JsonLines,
+
+    /// Parquet file
+    #[cfg(feature = "parquet")]
+    Parquet,
+
+    /// Arrow IPC file or stream
+    #[cfg(feature = "arrow")]
+    Arrow,
+
+    /// dab-native binary file of fixed-width records, see [`binary`]
+    Binary,
+
+    /// ISO 20022 camt.053 bank-to-customer statement, see [`CamtReader`]
+    #[cfg(feature = "camt")]
+    Camt,
+
+    /// SQLite database whose transactions are read with a query, see [`SqliteReader`]
+    #[cfg(feature = "sqlite")]
+    Sqlite,
+}
+
+/// Extensions of compressed files, which are skipped to guess the format of the file from its extension
+const COMPRESSION_EXTENSIONS: [&str; 4] = ["gz", "gzip", "zst", "zstd"];
+
+/// Number of bytes read from the start of a file to detect its format
+const SNIFF_LEN: u64 = 4096;
+
+impl Format {
+    /// Guess the format of the file at `path` from its extension, skipping a compression extension such as in
+    /// `transactions.csv.gz`
+    /// Returns [`None`] if the extension is unknown
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let path = path.as_ref();
+        let mut extension = path.extension()?.to_str()?.to_ascii_lowercase();
+
+        if COMPRESSION_EXTENSIONS.contains(&extension.as_str()) {
+            let stem = Path::new(path.file_stem()?);
+            extension = stem.extension()?.to_str()?.to_ascii_lowercase();
+        }
+
+        extension.parse().ok()
+    }
+
+    /// Guess the format of a file from its first bytes, once decompressed: binary, SQLite, Parquet and Arrow magic bytes,
+    /// a JSON object, a camt.053 XML document or a CSV header row with `type` and `client` columns
+    /// Returns [`None`] if the format cannot be told
+    pub fn sniff(head: &[u8]) -> Option<Self> {
+        if head.starts_with(binary::MAGIC) {
+            return Some(Format::Binary);
+        }
+
+        #[cfg(feature = "sqlite")]
+        if head.starts_with(sqlite::MAGIC) {
+            return Some(Format::Sqlite);
+        }
+
+        #[cfg(feature = "parquet")]
+        if head.starts_with(b"PAR1") {
+            return Some(Format::Parquet);
+        }
+
+        // Arrow IPC streams start with a continuation marker rather than magic bytes
+        #[cfg(feature = "arrow")]
+        if head.starts_with(b"ARROW1") || head.starts_with(&[0xff; 4]) {
+            return Some(Format::Arrow);
+        }
+
+        let text = head.strip_prefix("\u{feff}".as_bytes()).unwrap_or(head);
+        let text = String::from_utf8_lossy(text);
+        let text = text.trim_start();
+
+        if text.starts_with('{') {
+            return Some(Format::JsonLines);
+        }
+
+        #[cfg(feature = "camt")]
+        if text.starts_with('<') && (text.contains("camt.053") || text.contains("BkToCstmrStmt")) {
+            return Some(Format::Camt);
+        }
+
+        let header = text.lines().next()?.to_ascii_lowercase();
+        let columns = header
+            .split([',', ';', '\t', '|'])
+            .map(|column| column.trim().trim_matches('"'))
+            .collect::<Vec<_>>();
+        (columns.contains(&"type") && columns.contains(&"client")).then_some(Format::Csv)
+    }
+
+    /// Detect the format of the file at `path` from its extension, see [`Format::from_path`], or else from its
+    /// first bytes, see [`Format::sniff`]. The content of the standard input is not sniffed since it could not be
+    /// read again
+    /// Returns [`None`] if the format cannot be told
+    pub fn detect(path: impl AsRef<Path>) -> io::Result<Option<Self>> {
+        let path = path.as_ref();
+        if let Some(format) = Self::from_path(path) {
+            return Ok(Some(format));
+        }
+        if path == Path::new("-") {
+            return Ok(None);
+        }
+
+        let mut head = Vec::new();
+        open(path)?.take(SNIFF_LEN).read_to_end(&mut head)?;
+
+        Ok(Self::sniff(&head))
+    }
+}
+
+/// Error raised when parsing an unknown [`Format`]
+#[derive(Debug, Error)]
+#[error(
+    "unknown format `{0}`, expected one of `csv`, `jsonl`, `parquet`, `arrow`, `dab`, `camt` or `sqlite`"
+)]
+pub struct ParseFormatError(String);
+
+impl FromStr for Format {
+    type Err = ParseFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "jsonl" | "ndjson" => Ok(Format::JsonLines),
+            #[cfg(feature = "parquet")]
+            "parquet" | "parq" => Ok(Format::Parquet),
+            #[cfg(feature = "arrow")]
+            "arrow" | "arrows" | "ipc" | "feather" => Ok(Format::Arrow),
+            "dab" => Ok(Format::Binary),
+            #[cfg(feature = "camt")]
+            "camt" | "xml" => Ok(Format::Camt),
+            #[cfg(feature = "sqlite")]
+            "sqlite" | "sqlite3" | "db" => Ok(Format::Sqlite),
+            _ => Err(ParseFormatError(s.to_string())),
+        }
+    }
+}
+
+/// Options applied when reading transactions
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ReadOptions {
+    /// Layout of CSV files
+    pub dialect: CsvDialect,
+
+    /// How amounts written as text are parsed
+    pub locale: Locale,
+
+    /// What to do with amounts that have too many decimal places
+    pub precision: Precision,
+
+    /// Number of threads parsing CSV files, see [`ParallelCsvReader`]. CSV files are parsed while being read
+    /// when lower than 2
+    pub threads: usize,
+
+    /// Whether to memory-map CSV files and parse them in place, see [`MmapCsvReader`]
+    #[cfg(feature = "mmap")]
+    pub mmap: bool,
+
+    /// How the entries of camt.053 statements are mapped to transactions
+    #[cfg(feature = "camt")]
+    pub camt: CamtOptions,
+
+    /// Query reading the transactions of SQLite databases, [`sqlite::DEFAULT_QUERY`] if [`None`]
+    #[cfg(feature = "sqlite")]
+    pub query: Option<String>,
+}
+
+/// Iterator over the records read from a file along with the [`SourceRow`] they have been read from
+pub type RecordRows<C, T> =
+    Box<dyn Iterator<Item = (SourceRow, Result<TransactionRow<C, T>, ReadError>)>>;
+
+/// Iterator over the transactions read from a file along with the [`SourceRow`] they have been read from
+pub type TransactionRows<C, T> = Box<dyn Iterator<Item = (SourceRow, ReadResult<C, T>)>>;
+
+/// Read the records of a file in the given `format`, before they are converted to transactions
+pub fn read_records<C: Id, T: Id>(
+    path: impl AsRef<Path>,
+    format: Format,
+    options: &ReadOptions,
+) -> anyhow::Result<RecordRows<C, T>> {
+    Ok(match format {
+        #[cfg(feature = "mmap")]
+        Format::Csv if options.mmap => {
+            if options.locale != Locale::Plain {
+                anyhow::bail!("memory-mapped CSV files can only hold plain amounts");
+            }
+            Box::new(MmapCsvReader::new(path, &options.dialect)?.into_rows())
+        }
+        Format::Csv if options.threads > 1 => {
+            Box::new(ParallelCsvReader::new(path, &options.dialect, options.threads)?.into_rows())
+        }
+        Format::Csv => {
+            Box::new(CsvReader::with_dialect(open(path)?, &options.dialect)?.into_rows())
+        }
+        Format::JsonLines => Box::new(JsonLinesReader::new(path)?.into_rows()),
+        #[cfg(feature = "parquet")]
+        Format::Parquet => Box::new(ParquetReader::new(path)?.into_rows()),
+        #[cfg(feature = "arrow")]
+        Format::Arrow => Box::new(ArrowReader::new(path)?.into_rows()),
+        Format::Binary => Box::new(BinaryReader::new(path)?.into_rows()),
+        #[cfg(feature = "camt")]
+        Format::Camt => Box::new(CamtReader::new(path, &options.camt)?.into_rows()),
+        #[cfg(feature = "sqlite")]
+        Format::Sqlite => {
+            let query = options.query.as_deref().unwrap_or(sqlite::DEFAULT_QUERY);
+            Box::new(SqliteReader::new(path, query)?.into_rows())
+        }
+    })
+}
+
+/// Read transactions from a file in the given `format` along with the [`SourceRow`] each transaction has been
+/// read from, so that malformed or rejected rows can be reported
+pub fn read_rows<C: Id, T: Id>(
+    path: impl AsRef<Path>,
+    format: Format,
+    options: &ReadOptions,
+) -> anyhow::Result<TransactionRows<C, T>> {
+    let (locale, precision) = (options.locale, options.precision);
+    let file: Arc<str> = path.as_ref().display().to_string().into();
+
+    Ok(Box::new(read_records(path, format, options)?.map(
+        move |(row, record)| {
+            let transaction = record
+                .and_then(|record| {
+                    record
+                        .into_transaction(locale, precision)
+                        .map_err(|e| ReadError::invalid(e).locate(row.line, row.byte))
+                })
+                .map(|transaction| transaction.with_provenance(row.provenance(Some(file.clone()))));
+            (row, transaction)
+        },
+    )))
+}
+
+/// Read the earliest timestamp of the well-formed records of a file in the given `format`
+/// Returns [`None`] if no well-formed record of the file has a timestamp
+pub fn earliest_timestamp<C: Id, T: Id>(
+    path: impl AsRef<Path>,
+    format: Format,
+    options: &ReadOptions,
+) -> anyhow::Result<Option<u64>> {
+    Ok(read_records::<C, T>(path, format, options)?
+        .filter_map(|(_, record)| record.ok()?.timestamp())
+        .min())
+}
+
+/// Order in which multiple files of transactions are read
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum FileOrder {
+    /// Order in which the files are given, the files matching a glob pattern being in lexicographic order of their
+    /// paths
+    #[default]
+    Name,
+
+    /// Order of the earliest `timestamp` of the transactions of every file. Files without timestamp come last
+    Timestamp,
+}
+
+/// Error raised when parsing an unknown [`FileOrder`]
+#[derive(Debug, Error)]
+#[error("unknown file order `{0}`, expected one of `name` or `timestamp`")]
+pub struct ParseFileOrderError(String);
+
+impl FromStr for FileOrder {
+    type Err = ParseFileOrderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "name" => Ok(FileOrder::Name),
+            "timestamp" => Ok(FileOrder::Timestamp),
+            _ => Err(ParseFileOrderError(s.to_string())),
+        }
+    }
+}
+
+/// Expand the glob patterns of `paths` and order the resulting files according to `order`. A file given more than
+/// once, or matched by several patterns, is only kept at its first position
+/// Returns every file to read along with its format, which is detected from the file unless a `format` is given
+pub fn files<C: Id, T: Id>(
+    paths: impl IntoIterator<Item = PathBuf>,
+    format: Option<Format>,
+    order: FileOrder,
+    options: &ReadOptions,
+) -> anyhow::Result<Vec<(PathBuf, Format)>> {
+    let mut files = Vec::new();
+    let mut seen = HashSet::new();
+
+    for path in paths {
+        let pattern = path.to_string_lossy().into_owned();
+        let matches = match pattern.contains(['*', '?', '[']) {
+            false => vec![path],
+            true => {
+                let mut matches = glob::glob(&pattern)?.collect::<Result<Vec<_>, _>>()?;
+                if matches.is_empty() {
+                    anyhow::bail!("no file matches `{pattern}`");
+                }
+                matches.sort();
+                matches
+            }
+        };
+
+        for path in matches {
+            // The same file may be given through different paths, such as `a.csv` and `./a.csv`
+            let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+            if seen.insert(canonical) {
+                files.push(path);
+            }
+        }
+    }
+
+    let mut files = files
+        .into_iter()
+        .map(|path| {
+            let format = match format {
+                Some(format) => format,
+                None => Format::detect(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?
+                    .unwrap_or_default(),
+            };
+            Ok((path, format))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    if order == FileOrder::Timestamp {
+        let mut timestamps = HashMap::new();
+        for (path, format) in &files {
+            if path == Path::new("-") {
+                anyhow::bail!("the standard input cannot be ordered by timestamp");
+            }
+            timestamps.insert(
+                path.clone(),
+                earliest_timestamp::<C, T>(path, *format, options)?,
+            );
+        }
+
+        // Files without timestamp come last, files with the same timestamp keep their order
+        files.sort_by_key(|(path, _)| {
+            let timestamp = timestamps[path];
+            (timestamp.is_none(), timestamp)
+        });
+    }
+
+    Ok(files)
+}
+
+type ReadResult<C, T> = Result<Transaction<C, T>, ReadError>;
+
 /// Read transactions from a CSV file
-/// Returns a success iterator over the [`Transaction`] read from the CSV file or an IO error
-pub fn read_csv(
+/// Returns a success iterator over the [`Transaction`] read from the CSV file, or the [`ReadError`] of each record
+/// that could not be read, or an IO error
+pub fn read_csv<C: Id, T: Id>(
+    path: impl AsRef<Path>,
+) -> anyhow::Result<impl Iterator<Item = ReadResult<C, T>>> {
+    Ok(read_csv_rows(path)?.map(|(_, transaction)| transaction))
+}
+
+/// Read transactions from a CSV file along with the [`SourceRow`] each transaction has been read from,
+/// so that malformed or rejected rows can be reported
+pub fn read_csv_rows<C: Id, T: Id>(
     path: impl AsRef<Path>,
-) -> anyhow::Result<impl Iterator<Item = anyhow::Result<Transaction>>> {
-    Ok(read(csv::CsvReader::new(path)?))
+) -> anyhow::Result<impl Iterator<Item = (SourceRow, ReadResult<C, T>)>> {
+    let file: Arc<str> = path.as_ref().display().to_string().into();
+
+    Ok(CsvReader::new(path)?.into_rows().map(move |(row, record)| {
+        let transaction = convert(record)
+            .map(|transaction| transaction.with_provenance(row.provenance(Some(file.clone()))))
+            .map_err(|e| e.at_line(row.line));
+        (row, transaction)
+    }))
+}
+
+/// Read accounts from a CSV file with the same columns as the [`crate::output::CsvWriter`] output
+/// Returns a success iterator over the [`Account`] read from the CSV file or an IO error
+pub fn read_accounts_csv<C: Id>(
+    path: impl AsRef<Path>,
+) -> anyhow::Result<impl Iterator<Item = anyhow::Result<Account<C>>>> {
+    Ok(csv::read_accounts(path)?.map(|account| account.map_err(Into::into)))
 }
 
 /// Read transactions from a [`Reader`]
-/// Returns an iterator over the [`Transaction`] read from the reader
-fn read<R: Reader>(reader: R) -> impl Iterator<Item = anyhow::Result<Transaction>> {
-    reader.into_iter().map(|record| match record {
-        Ok(record) => record.try_into().map_err(|e| anyhow!("{}", e.into())),
-        Err(e) => Err(anyhow!("{}", e.into())),
-    })
+/// Returns an iterator over the [`Transaction`] read from the reader, or the [`ReadError`] of each record that could
+/// not be read or converted
+pub fn read<C: Id, T: Id, R: Reader<C, T>>(reader: R) -> impl Iterator<Item = ReadResult<C, T>> {
+    reader.into_iter().map(convert)
+}
+
+/// Convert a record yielded by a [`Reader`] to a [`Transaction`]
+fn convert<C: Id, T: Id, I, E>(record: Result<I, E>) -> ReadResult<C, T>
+where
+    I: TryInto<Transaction<C, T>>,
+    I::Error: Into<BoxError>,
+    E: Into<BoxError>,
+{
+    match record {
+        Ok(record) => record.try_into().map_err(ReadError::invalid),
+        Err(e) => Err(ReadError::read(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "type, client, tx, amount\ndeposit, 1, 1, 1.5\nwithdrawal, 1, 2, 0.5\n";
+
+    #[cfg(feature = "compression")]
+    fn transactions(data: Vec<u8>) -> Vec<Transaction> {
+        let reader = CsvReader::from_reader(decompress(io::Cursor::new(data)).unwrap()).unwrap();
+
+        read(reader).collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn decompress_from_magic_bytes() {
+        use std::io::Write;
+
+        let plain = transactions(CSV.as_bytes().to_vec());
+        assert_eq!(plain.len(), 2);
+
+        let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gzip.write_all(CSV.as_bytes()).unwrap();
+        let gzip = transactions(gzip.finish().unwrap());
+
+        let zstd = transactions(zstd::encode_all(CSV.as_bytes(), 0).unwrap());
+
+        for transactions in [gzip, zstd] {
+            assert_eq!(format!("{transactions:?}"), format!("{plain:?}"));
+        }
+    }
+
+    /// Reader returning a single byte on every read, as pipes may do
+    #[cfg(feature = "compression")]
+    struct OneByte<R>(R);
+
+    #[cfg(feature = "compression")]
+    impl<R: Read> Read for OneByte<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "compression")]
+    fn decompress_from_short_reads() {
+        let data = zstd::encode_all(CSV.as_bytes(), 0).unwrap();
+        let mut text = String::new();
+        decompress(OneByte(io::Cursor::new(data)))
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, CSV);
+
+        // Data shorter than the magic bytes is read as is
+        let mut text = String::new();
+        decompress(OneByte(&b"ab"[..]))
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(text, "ab");
+    }
+
+    #[test]
+    fn locate_read_errors() {
+        let data = "type,client,tx,amount\ndeposit,1,1,1.5\n\ndeposit,x,2,1.0\nwithdrawal,1,3,\n";
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dab-locate-read-errors.csv");
+        std::fs::write(&path, data).unwrap();
+        let errors = read_csv::<u16, u32>(&path)
+            .unwrap()
+            .filter_map(Result::err)
+            .collect::<Vec<_>>();
+
+        assert_eq!(errors.len(), 2);
+        let ReadError::Parse { position, source } = &errors[0] else {
+            panic!("expected a parse error, got {:?}", errors[0]);
+        };
+        assert_eq!(
+            (position.line, position.byte, position.field.as_deref()),
+            (Some(4), Some(39), Some("client"))
+        );
+        assert!(source.is::<::csv::Error>());
+        assert!(errors[0]
+            .to_string()
+            .starts_with("line 4, byte 39, field `client`: "));
+
+        let ReadError::Invalid { position, source } = &errors[1] else {
+            panic!("expected an invalid record error, got {:?}", errors[1]);
+        };
+        assert_eq!(position.line, Some(5));
+        assert_eq!(position.field.as_deref(), Some("amount"));
+        assert!(matches!(
+            source.downcast_ref::<AmountError>(),
+            Some(AmountError::Missing("withdrawal"))
+        ));
+    }
+
+    #[test]
+    fn trace_transactions_to_their_rows() {
+        let provenances = |name: &str, format: Format, data: &str| {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join(name);
+            std::fs::write(&path, data).unwrap();
+            let provenances = read_rows::<u16, u32>(&path, format, &ReadOptions::default())
+                .unwrap()
+                .map(|(_, transaction)| {
+                    let provenance = transaction.unwrap().provenance.unwrap();
+                    assert_eq!(
+                        provenance.file.as_deref(),
+                        Some(&*path.display().to_string())
+                    );
+                    (provenance.line, provenance.byte)
+                })
+                .collect::<Vec<_>>();
+
+            provenances
+        };
+
+        assert_eq!(
+            provenances("dab-trace-transactions.csv", Format::Csv, CSV),
+            [(2, Some(25)), (3, Some(44))]
+        );
+        assert_eq!(
+            provenances(
+                "dab-trace-transactions.jsonl",
+                Format::JsonLines,
+                "{\"type\": \"deposit\", \"client\": 1, \"tx\": 1, \"amount\": 1.5}\r\n\n\
+                 {\"type\": \"dispute\", \"client\": 1, \"tx\": 1}\n"
+            ),
+            [(1, Some(0)), (3, Some(59))]
+        );
+    }
+
+    #[test]
+    fn detect_format() {
+        assert_eq!(
+            Format::from_path("a/transactions.JSONL"),
+            Some(Format::JsonLines)
+        );
+        assert_eq!(Format::from_path("transactions.csv.gz"), Some(Format::Csv));
+        assert_eq!(
+            Format::from_path("transactions.ndjson.zst"),
+            Some(Format::JsonLines)
+        );
+        assert_eq!(Format::from_path("transactions.gz"), None);
+        assert_eq!(Format::from_path("transactions"), None);
+
+        assert_eq!(Format::sniff(CSV.as_bytes()), Some(Format::Csv));
+        assert_eq!(
+            Format::sniff(b"\xef\xbb\xbf\"Type\";\"Client\";\"Tx\"\n"),
+            Some(Format::Csv)
+        );
+        assert_eq!(
+            Format::sniff(b"\n  {\"type\": \"deposit\"}\n"),
+            Some(Format::JsonLines)
+        );
+        #[cfg(feature = "parquet")]
+        assert_eq!(Format::sniff(b"PAR1\x15\x04"), Some(Format::Parquet));
+        #[cfg(feature = "arrow")]
+        assert_eq!(Format::sniff(b"\xff\xff\xff\xff\x10"), Some(Format::Arrow));
+        assert_eq!(Format::sniff(b"DABT\x01\x00"), Some(Format::Binary));
+        #[cfg(feature = "camt")]
+        assert_eq!(
+            Format::sniff(b"<?xml version=\"1.0\"?>\n<Document xmlns=\"urn:iso:std:iso:20022:tech:xsd:camt.053.001.02\">"),
+            Some(Format::Camt)
+        );
+        #[cfg(feature = "sqlite")]
+        assert_eq!(
+            Format::sniff(b"SQLite format 3\0\x10\x00"),
+            Some(Format::Sqlite)
+        );
+        assert_eq!(Format::sniff(b"deposit,1,1,1.5\n"), None);
+
+        // Files without a known extension are sniffed, once decompressed
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dab-detect-format");
+        std::fs::write(&path, "{\"type\": \"deposit\"}\n").unwrap();
+        let format = Format::detect(&path).unwrap();
+        assert_eq!(format, Some(Format::JsonLines));
+    }
+
+    #[test]
+    fn expand_and_order_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let dir = dir.path();
+
+        // The first transaction of `a.csv` is the latest but the file has the earliest transaction
+        let header = "type,client,tx,amount,batch,timestamp\n";
+        for (name, rows) in [
+            ("a.csv", "deposit,1,1,1.0,,300\ndeposit,1,2,1.0,,100\n"),
+            ("b.csv", "deposit,1,3,1.0,,200\n"),
+            ("c.csv", "deposit,1,4,1.0,,\n"),
+        ] {
+            std::fs::write(dir.join(name), format!("{header}{rows}")).unwrap();
+        }
+
+        let names = |paths: &[&str], order| {
+            let paths = paths.iter().map(|path| dir.join(path));
+            files::<u16, u32>(paths, None, order, &ReadOptions::default())
+                .unwrap()
+                .into_iter()
+                .map(|(path, format)| {
+                    assert_eq!(format, Format::Csv);
+                    path.file_name().unwrap().to_string_lossy().into_owned()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // Files given explicitly keep their order while the files matching a pattern are sorted by name
+        assert_eq!(
+            names(&["c.csv", "a.csv"], FileOrder::Name),
+            ["c.csv", "a.csv"]
+        );
+        assert_eq!(
+            names(&["*.csv"], FileOrder::Name),
+            ["a.csv", "b.csv", "c.csv"]
+        );
+
+        // Files given more than once are kept at their first position
+        assert_eq!(
+            names(&["b.csv", "*.csv", "./b.csv"], FileOrder::Name),
+            ["b.csv", "a.csv", "c.csv"]
+        );
+
+        // Files are ordered by their earliest timestamp, files without timestamp last
+        assert_eq!(
+            names(&["c.csv", "b.csv", "a.csv"], FileOrder::Timestamp),
+            ["a.csv", "b.csv", "c.csv"]
+        );
+    }
 }
@@ -0,0 +1,452 @@
+use std::{
+    collections::BTreeMap,
+    io, mem,
+    panic::{self, AssertUnwindSafe},
+    path::Path,
+    sync::{mpsc, Arc, Mutex},
+    thread, vec,
+};
+
+use crate::transaction::Id;
+
+use super::{
+    csv::{self, CsvDialect, Layout},
+    AmountError, ReadError, Reader, SourceRow, TransactionRow,
+};
+
+/// Default number of bytes of the chunks parsed by a thread
+const CHUNK_SIZE: usize = 1 << 20;
+
+/// Number of bytes read at once from the underlying reader
+const READ_SIZE: usize = 64 << 10;
+
+/// Number of chunks read ahead per thread
+const CHUNKS_PER_THREAD: usize = 2;
+
+/// A [`Reader`] of transactions from CSV data that parses records on several threads
+///
+/// The data is split into chunks of whole records that are parsed in parallel, while records are yielded in their
+/// original order. Chunks are read lazily, a couple of chunks per thread ahead of the records being yielded, so that
+/// memory stays bounded whatever the size of the data
+pub struct ParallelCsvReader<C = u16, T = u32, R = Box<dyn io::Read>> {
+    rows: Rows<C, T, R>,
+}
+
+impl<C: Id, T: Id> ParallelCsvReader<C, T> {
+    /// Open the CSV file at `path`, or the standard input if `path` is `-`, and parse it on `threads` threads.
+    /// Compressed files are transparently decompressed, see [`super::open`]
+    pub fn new(
+        path: impl AsRef<Path>,
+        dialect: &CsvDialect,
+        threads: usize,
+    ) -> anyhow::Result<Self> {
+        Self::from_reader(super::open(path)?, dialect, threads)
+    }
+}
+
+impl<C: Id, T: Id, R: io::Read> ParallelCsvReader<C, T, R> {
+    /// Read CSV data laid out according to the `dialect` from `reader` and parse it on `threads` threads,
+    /// at least one
+    pub fn from_reader(reader: R, dialect: &CsvDialect, threads: usize) -> anyhow::Result<Self> {
+        let mut splitter = Splitter {
+            reader,
+            buf: Vec::new(),
+            quote: dialect.quote,
+            line: 1,
+            offset: 0,
+            eof: false,
+        };
+
+        // The header row is the first record that is not made of blank lines
+        let header = if dialect.has_headers {
+            let mut header = ::csv::StringRecord::new();
+            while let Some((_, _, bytes)) = splitter.next(1)? {
+                if csv::dialect_builder(dialect)
+                    .from_reader(bytes.as_slice())
+                    .read_record(&mut header)?
+                {
+                    break;
+                }
+            }
+            Some(header)
+        } else {
+            None
+        };
+        let layout = Arc::new(Layout::new(header.as_ref(), dialect));
+
+        let threads = threads.max(1);
+        let (chunks, receiver) = mpsc::channel::<Chunk>();
+        let (sender, results) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..threads)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                let sender = sender.clone();
+                let layout = Arc::clone(&layout);
+
+                thread::spawn(move || loop {
+                    let Ok(chunk) = receiver.lock().unwrap().recv() else {
+                        break;
+                    };
+
+                    let index = chunk.index;
+                    let rows = parse(chunk, |bytes, line, offset| {
+                        csv::chunk(bytes, line, offset, &layout).collect()
+                    });
+                    if sender.send((index, rows)).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            rows: Rows {
+                splitter,
+                chunk_size: CHUNK_SIZE,
+                window: threads * CHUNKS_PER_THREAD,
+                chunks: Some(chunks),
+                results,
+                workers,
+                pending: BTreeMap::new(),
+                current: Vec::new().into_iter(),
+                sent: 0,
+                next: 0,
+                done: false,
+                error: None,
+            },
+        })
+    }
+
+    /// Split the data into chunks of about `chunk_size` bytes instead of 1 MiB
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.rows.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Convert the reader to an iterator over the records along with the [`SourceRow`] they have been read from
+    pub fn into_rows(self) -> Rows<C, T, R> {
+        self.rows
+    }
+}
+
+/// Splits CSV data into chunks of whole records, keeping track of quotes so that line terminators within quoted
+/// fields do not end a chunk
+struct Splitter<R> {
+    reader: R,
+
+    /// Bytes read but not split yet
+    buf: Vec<u8>,
+
+    /// Byte quoting fields, if any
+    quote: Option<u8>,
+
+    /// Line of the first byte of `buf`, starting at 1
+    line: u64,
+
+    /// Byte offset of the first byte of `buf`
+    offset: u64,
+
+    /// Whether the underlying reader has been read until its end
+    eof: bool,
+}
+
+impl<R: io::Read> Splitter<R> {
+    /// Split the next chunk of at least `size` bytes, unless the data ends first, along with its first line and
+    /// byte offset
+    /// Returns [`None`] at the end of the data
+    fn next(&mut self, size: usize) -> io::Result<Option<(u64, u64, Vec<u8>)>> {
+        let mut position = 0;
+        let mut quoted = false;
+
+        let end = 'split: loop {
+            while position < self.buf.len() {
+                let byte = self.buf[position];
+                position += 1;
+
+                if Some(byte) == self.quote {
+                    quoted = !quoted;
+                } else if byte == b'\n' && !quoted && position >= size {
+                    break 'split position;
+                }
+            }
+
+            if self.eof {
+                break self.buf.len();
+            }
+            self.fill(size)?;
+        };
+
+        if end == 0 {
+            return Ok(None);
+        }
+
+        let rest = self.buf.split_off(end);
+        let bytes = mem::replace(&mut self.buf, rest);
+
+        let (line, offset) = (self.line, self.offset);
+        self.line += bytes.iter().filter(|&&b| b == b'\n').count() as u64;
+        self.offset += bytes.len() as u64;
+
+        Ok(Some((line, offset, bytes)))
+    }
+
+    /// Read at most `size` more bytes from the underlying reader
+    fn fill(&mut self, size: usize) -> io::Result<()> {
+        let len = self.buf.len();
+        self.buf.resize(len + size.max(READ_SIZE), 0);
+
+        let read = loop {
+            match self.reader.read(&mut self.buf[len..]) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.buf.truncate(len);
+                    return Err(e);
+                }
+            }
+        };
+
+        self.buf.truncate(len + read);
+        self.eof = read == 0;
+
+        Ok(())
+    }
+}
+
+/// A chunk of CSV data sent to a parsing thread
+struct Chunk {
+    /// Index of the chunk in the data, starting at 0
+    index: usize,
+
+    /// Line of the first byte of the chunk
+    line: u64,
+
+    /// Byte offset of the first byte of the chunk
+    offset: u64,
+
+    bytes: Vec<u8>,
+}
+
+type Item<C, T> = (SourceRow, Record<C, T>);
+
+/// Parse a `chunk` into its records with `records`, called with the bytes, first line and byte offset of the chunk.
+/// A panic while parsing is turned into an error located at the start of the chunk, so that the records of the chunk
+/// are not silently lost and the records of the following chunks are still yielded
+fn parse<C, T>(
+    chunk: Chunk,
+    records: impl FnOnce(Vec<u8>, u64, u64) -> Vec<Item<C, T>>,
+) -> Vec<Item<C, T>> {
+    let Chunk {
+        line,
+        offset,
+        bytes,
+        ..
+    } = chunk;
+
+    panic::catch_unwind(AssertUnwindSafe(|| records(bytes, line, offset))).unwrap_or_else(|_| {
+        let error = ReadError::read("a CSV parsing thread panicked while parsing the records");
+
+        vec![(
+            SourceRow::new(line, Some(offset), String::new()),
+            Err(error.locate(line, Some(offset))),
+        )]
+    })
+}
+
+/// Iterator over the records of CSV data parsed on several threads, in their original order, along with the
+/// [`SourceRow`] they have been read from
+pub struct Rows<C, T, R = Box<dyn io::Read>> {
+    splitter: Splitter<R>,
+    chunk_size: usize,
+
+    /// Maximum number of chunks sent to the threads and not yielded yet
+    window: usize,
+
+    chunks: Option<mpsc::Sender<Chunk>>,
+    results: mpsc::Receiver<(usize, Vec<Item<C, T>>)>,
+    workers: Vec<thread::JoinHandle<()>>,
+
+    /// Chunks parsed before the chunks that precede them
+    pending: BTreeMap<usize, Vec<Item<C, T>>>,
+
+    /// Records of the chunk being yielded
+    current: vec::IntoIter<Item<C, T>>,
+
+    /// Number of chunks sent to the threads
+    sent: usize,
+
+    /// Index of the next chunk to yield
+    next: usize,
+
+    /// Whether the data has been split until its end
+    done: bool,
+
+    /// Error raised while reading the data, yielded after the records read before it
+    error: Option<io::Error>,
+}
+
+impl<C: Id, T: Id, R: io::Read> Rows<C, T, R> {
+    /// Split chunks and send them to the threads until the window is full or the data ends
+    fn dispatch(&mut self) {
+        while !self.done && self.sent - self.next < self.window {
+            match self.splitter.next(self.chunk_size) {
+                Ok(Some((line, offset, bytes))) => {
+                    let chunk = Chunk {
+                        index: self.sent,
+                        line,
+                        offset,
+                        bytes,
+                    };
+                    if let Some(chunks) = &self.chunks {
+                        chunks
+                            .send(chunk)
+                            .expect("CSV parsing threads should be running");
+                    }
+                    self.sent += 1;
+                }
+                Ok(None) => self.done = true,
+                Err(e) => {
+                    self.error = Some(e);
+                    self.done = true;
+                }
+            }
+        }
+    }
+}
+
+impl<C: Id, T: Id, R: io::Read> Iterator for Rows<C, T, R> {
+    type Item = Item<C, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.current.next() {
+                return Some(item);
+            }
+
+            self.dispatch();
+            if self.next == self.sent {
+                let error = self.error.take()?;
+                return Some((SourceRow::default(), Err(error.into())));
+            }
+
+            let rows = loop {
+                if let Some(rows) = self.pending.remove(&self.next) {
+                    break rows;
+                }
+
+                let (index, rows) = self
+                    .results
+                    .recv()
+                    .expect("CSV parsing threads should be running");
+                self.pending.insert(index, rows);
+            };
+
+            self.next += 1;
+            self.current = rows.into_iter();
+        }
+    }
+}
+
+impl<C, T, R> Drop for Rows<C, T, R> {
+    fn drop(&mut self) {
+        // Threads stop once every chunk has been received
+        drop(self.chunks.take());
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+type Record<C, T> = Result<TransactionRow<C, T>, ReadError>;
+
+impl<C: Id, T: Id, R: io::Read> Reader<C, T> for ParallelCsvReader<C, T, R> {
+    type IntoError = AmountError;
+    type Item = TransactionRow<C, T>;
+    type Error = ReadError;
+
+    type Iterator = std::iter::Map<Rows<C, T, R>, fn(Item<C, T>) -> Record<C, T>>;
+
+    fn into_iter(self) -> Self::Iterator {
+        self.rows.map(|(_, record)| record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::*;
+
+    #[test]
+    fn parse_chunks_in_order() {
+        let mut data = String::from("type,client,tx,amount\n");
+        for tx in 1..=500 {
+            match tx % 50 {
+                // Quoted line terminators do not split records
+                0 => writeln!(data, "deposit,1,{tx},\"1.5\n\"").unwrap(),
+                1 => writeln!(data, "bogus,1,{tx},1.0\r\n").unwrap(),
+                _ => writeln!(data, "deposit,1,{tx},1.0").unwrap(),
+            }
+        }
+
+        let rows = |threads| {
+            ParallelCsvReader::<u16, u32, &[u8]>::from_reader(
+                data.as_bytes(),
+                &CsvDialect::default(),
+                threads,
+            )
+            .unwrap()
+            .with_chunk_size(64)
+            .into_rows()
+            .map(|(row, record)| {
+                (
+                    row,
+                    record
+                        .map(|r| format!("{r:?}"))
+                        .map_err(|e| e.position().clone()),
+                )
+            })
+            .collect::<Vec<_>>()
+        };
+        let sequential = super::super::CsvReader::<u16, u32, &[u8]>::from_reader(data.as_bytes())
+            .unwrap()
+            .into_rows()
+            .map(|(row, record)| {
+                (
+                    row,
+                    record
+                        .map(|r| format!("{r:?}"))
+                        .map_err(|e| e.position().clone()),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(sequential.len(), 500);
+        assert_eq!(rows(1), sequential);
+        assert_eq!(rows(4), sequential);
+    }
+
+    #[test]
+    fn report_panicking_chunks() {
+        let chunk = Chunk {
+            index: 3,
+            line: 42,
+            offset: 1024,
+            bytes: b"deposit,1,1,1.0\n".to_vec(),
+        };
+
+        // The chunk is reported as an error instead of leaving the iterator waiting for its records
+        let rows = parse::<u16, u32>(chunk, |_, _, _| panic!("bug while parsing"));
+        assert_eq!(rows.len(), 1);
+
+        let (row, record) = &rows[0];
+        assert_eq!((row.line, row.byte), (42, Some(1024)));
+        let error = record.as_ref().unwrap_err();
+        assert_eq!(
+            (error.position().line, error.position().byte),
+            (Some(42), Some(1024))
+        );
+    }
+}
@@ -0,0 +1,294 @@
+use std::{fs::File, marker::PhantomData, path::Path, str::FromStr};
+
+use parquet::{
+    file::reader::SerializedFileReader,
+    record::{reader::RowIter, Field, Row},
+};
+
+use crate::transaction::Id;
+
+use super::{
+    amount::{Amount, AmountError},
+    FieldError, ReadError, Reader, SourceRow, TransactionRow,
+};
+
+/// A [`Reader`] of transactions from a Parquet file with the columns of a [`TransactionRow`]
+/// Row groups are read lazily, one at a time, while iterating over the transactions
+pub struct ParquetReader<C = u16, T = u32> {
+    rows: Rows<C, T>,
+}
+
+impl<C: Id, T: Id> ParquetReader<C, T> {
+    /// Open the Parquet file at `path`
+    pub fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let reader = SerializedFileReader::new(File::open(path)?)?;
+
+        Ok(Self {
+            rows: Rows {
+                it: reader.into_iter(),
+                index: 0,
+                _marker: PhantomData,
+            },
+        })
+    }
+
+    /// Convert the reader to an iterator over the records along with the [`SourceRow`] they have been read from
+    /// The line of a Parquet row is its row number, starting at 1
+    pub fn into_rows(self) -> Rows<C, T> {
+        self.rows
+    }
+}
+
+/// Iterator over the records of a Parquet file along with the [`SourceRow`] they have been read from
+pub struct Rows<C, T> {
+    it: RowIter<'static>,
+
+    /// Number of rows read so far
+    index: u64,
+
+    _marker: PhantomData<fn() -> (C, T)>,
+}
+
+impl<C: Id, T: Id> Iterator for Rows<C, T> {
+    type Item = (SourceRow, Record<C, T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let row = self.it.next()?;
+        self.index += 1;
+
+        let (text, record) = match row {
+            Ok(row) => (row.to_string(), record(&row)),
+            Err(e) => (String::new(), Err(ReadError::read(e))),
+        };
+        let record = record.map_err(|e| e.locate(self.index, None));
+
+        Some((SourceRow::new(self.index, None, text), record))
+    }
+}
+
+/// Convert a Parquet [`Row`] to a [`TransactionRow`], ignoring unknown columns
+fn record<C: Id, T: Id>(row: &Row) -> Record<C, T> {
+    let mut kind = None;
+    let mut client = None;
+    let mut tx = None;
+    let mut amount = None;
+    let mut batch = None;
+    let mut timestamp = None;
+
+    for (name, field) in row.get_column_iter() {
+        match name.as_str() {
+            "type" => match field {
+                Field::Str(s) => kind = Some(s.as_str()),
+                _ => return Err(unexpected(name, "a string", field)),
+            },
+            "client" => client = Some(parse(name, field)?),
+            "tx" => tx = Some(parse(name, field)?),
+            "amount" => amount = number(name, field)?,
+            "batch" => {
+                batch = match field {
+                    Field::Null => None,
+                    _ => Some(parse(name, field)?),
+                }
+            }
+            "timestamp" => {
+                timestamp = match field {
+                    Field::Null => None,
+                    _ => Some(parse(name, field)?),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    TransactionRow::from_columns(
+        kind.ok_or_else(|| ReadError::field("type", FieldError::Missing))?,
+        client.ok_or_else(|| ReadError::field("client", FieldError::Missing))?,
+        tx.ok_or_else(|| ReadError::field("tx", FieldError::Missing))?,
+        amount,
+        batch,
+        timestamp,
+    )
+}
+
+/// Parse an identifier stored as an integer or a string
+fn parse<I: FromStr>(name: &str, field: &Field) -> Result<I, ReadError> {
+    let value = match field {
+        Field::Str(s) => s.clone(),
+        Field::Byte(_)
+        | Field::Short(_)
+        | Field::Int(_)
+        | Field::Long(_)
+        | Field::UByte(_)
+        | Field::UShort(_)
+        | Field::UInt(_)
+        | Field::ULong(_) => field.to_string(),
+        _ => return Err(unexpected(name, "an integer or a string", field)),
+    };
+
+    value
+        .parse()
+        .map_err(|_| ReadError::field(name, FieldError::Invalid(value)))
+}
+
+/// Read an optional amount stored as a floating point number, an integer or a string
+fn number(name: &str, field: &Field) -> Result<Option<Amount>, ReadError> {
+    Ok(match field {
+        Field::Null => None,
+        Field::Float(v) => Some(Amount::Number(*v as f64)),
+        Field::Double(v) => Some(Amount::Number(*v)),
+        Field::Int(v) => Some(Amount::Number(*v as f64)),
+        Field::Long(v) => Some(Amount::Number(*v as f64)),
+        Field::Str(s) => Some(Amount::Text(s.clone())),
+        _ => return Err(unexpected(name, "a number or a string", field)),
+    })
+}
+
+/// Error raised when the `field` of the column `name` is not of the `expected` type
+fn unexpected(name: &str, expected: &'static str, field: &Field) -> ReadError {
+    let error = match field {
+        Field::Null => FieldError::Missing,
+        _ => FieldError::Unexpected {
+            expected,
+            found: field.to_string(),
+        },
+    };
+
+    ReadError::field(name, error)
+}
+
+type Record<C, T> = Result<TransactionRow<C, T>, ReadError>;
+
+impl<C: Id, T: Id> Reader<C, T> for ParquetReader<C, T> {
+    type IntoError = AmountError;
+    type Item = TransactionRow<C, T>;
+    type Error = ReadError;
+
+    type Iterator = std::iter::Map<Rows<C, T>, fn((SourceRow, Record<C, T>)) -> Record<C, T>>;
+
+    fn into_iter(self) -> Self::Iterator {
+        self.rows.map(|(_, record)| record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parquet::{
+        data_type::{ByteArray, ByteArrayType, DoubleType, Int32Type, Int64Type},
+        file::{properties::WriterProperties, writer::SerializedFileWriter},
+        schema::parser::parse_message_type,
+    };
+
+    use crate::transaction::{Transaction, TransactionOperation};
+
+    use super::*;
+
+    type Row<'a> = (&'a str, i32, i64, Option<f64>);
+
+    /// Write a Parquet file with one row group per element of `groups`
+    fn write(path: &Path, groups: &[&[Row]]) {
+        let schema = parse_message_type(
+            "message transactions {
+                REQUIRED BYTE_ARRAY type (UTF8);
+                REQUIRED INT32 client;
+                REQUIRED INT64 tx;
+                OPTIONAL DOUBLE amount;
+            }",
+        )
+        .unwrap();
+        let properties = Arc::new(WriterProperties::builder().build());
+        let mut writer =
+            SerializedFileWriter::new(File::create(path).unwrap(), Arc::new(schema), properties)
+                .unwrap();
+
+        for rows in groups {
+            let mut group = writer.next_row_group().unwrap();
+
+            let types = rows
+                .iter()
+                .map(|r| ByteArray::from(r.0))
+                .collect::<Vec<_>>();
+            let mut column = group.next_column().unwrap().unwrap();
+            column
+                .typed::<ByteArrayType>()
+                .write_batch(&types, None, None)
+                .unwrap();
+            column.close().unwrap();
+
+            let clients = rows.iter().map(|r| r.1).collect::<Vec<_>>();
+            let mut column = group.next_column().unwrap().unwrap();
+            column
+                .typed::<Int32Type>()
+                .write_batch(&clients, None, None)
+                .unwrap();
+            column.close().unwrap();
+
+            let txs = rows.iter().map(|r| r.2).collect::<Vec<_>>();
+            let mut column = group.next_column().unwrap().unwrap();
+            column
+                .typed::<Int64Type>()
+                .write_batch(&txs, None, None)
+                .unwrap();
+            column.close().unwrap();
+
+            let amounts = rows.iter().filter_map(|r| r.3).collect::<Vec<_>>();
+            let levels = rows
+                .iter()
+                .map(|r| r.3.map_or(0, |_| 1))
+                .collect::<Vec<_>>();
+            let mut column = group.next_column().unwrap().unwrap();
+            column
+                .typed::<DoubleType>()
+                .write_batch(&amounts, Some(&levels), None)
+                .unwrap();
+            column.close().unwrap();
+
+            group.close().unwrap();
+        }
+
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn read_row_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dab-read-row-groups.parquet");
+        write(
+            &path,
+            &[
+                &[("deposit", 1, 1, Some(2.5)), ("dispute", 1, 1, None)],
+                &[("withdrawal", 2, 2, None), ("resolve", 1, 1, None)],
+            ],
+        );
+
+        let rows = ParquetReader::<u16, u32>::new(&path)
+            .unwrap()
+            .into_rows()
+            .map(|(row, record)| {
+                (
+                    row.line,
+                    record.and_then(|r| r.try_into().map_err(ReadError::invalid)),
+                )
+            })
+            .collect::<Vec<(u64, Result<Transaction, ReadError>)>>();
+
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows.iter().map(|r| r.0).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert!(matches!(
+            rows[0].1.as_ref().map(|t| t.operation),
+            Ok(TransactionOperation::Deposit(amount)) if amount == 2.5
+        ));
+        assert!(matches!(
+            rows[1].1.as_ref().map(|t| t.operation),
+            Ok(TransactionOperation::Dispute)
+        ));
+
+        // Withdrawals without an amount are rejected like in CSV files
+        assert!(rows[2].1.is_err());
+        assert!(matches!(
+            rows[3].1.as_ref().map(|t| t.operation),
+            Ok(TransactionOperation::Resolve)
+        ));
+    }
+}
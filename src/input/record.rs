@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+
+use crate::transaction::{Id, Transaction, TransactionOperation};
+
+use super::{
+    amount::{self, Amount, AmountError, Locale, Precision},
+    ReadError,
+};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TransactionType {
+    Deposit,
+    Withdrawal,
+    Dispute,
+    Resolve,
+    Chargeback,
+}
+
+impl TransactionType {
+    fn name(self) -> &'static str {
+        match self {
+            TransactionType::Deposit => "deposit",
+            TransactionType::Withdrawal => "withdrawal",
+            TransactionType::Dispute => "dispute",
+            TransactionType::Resolve => "resolve",
+            TransactionType::Chargeback => "chargeback",
+        }
+    }
+}
+
+/// A transaction row with `type`, `client`, `tx`, `amount` and optional `batch` and `timestamp` columns,
+/// as read by every [`super::Reader`]. Rows of other formats can be deserialized with serde from the same columns.
+/// Rows are validated when converted to a [`Transaction`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransactionRow<C = u16, T = u32> {
+    r#type: TransactionType,
+    client: C,
+    tx: T,
+    amount: Option<Amount>,
+
+    /// Optional column grouping consecutive rows into batches that are applied atomically
+    #[serde(default)]
+    batch: Option<u64>,
+
+    /// Optional column holding the time of the transaction as seconds since the Unix epoch
+    #[serde(default)]
+    timestamp: Option<u64>,
+}
+
+impl<C, T> TransactionRow<C, T> {
+    /// Build a row from the values of its columns, for readers of formats that are not deserialized with serde
+    pub(crate) fn from_columns(
+        kind: &str,
+        client: C,
+        tx: T,
+        amount: Option<Amount>,
+        batch: Option<u64>,
+        timestamp: Option<u64>,
+    ) -> Result<Self, ReadError> {
+        use serde::de::{self, IntoDeserializer};
+
+        let r#type = TransactionType::deserialize(kind.into_deserializer())
+            .map_err(|e: de::value::Error| ReadError::field("type", e))?;
+
+        Ok(Self {
+            r#type,
+            client,
+            tx,
+            amount,
+            batch,
+            timestamp,
+        })
+    }
+
+    /// Replace the amount of the row, for readers that do not deserialize amounts with serde
+    pub(super) fn with_amount(mut self, amount: Option<Amount>) -> Self {
+        self.amount = amount;
+        self
+    }
+
+    /// Time of the transaction as seconds since the Unix epoch, if the row has one
+    pub fn timestamp(&self) -> Option<u64> {
+        self.timestamp
+    }
+}
+
+impl<C: Id, T: Id> TransactionRow<C, T> {
+    /// Validate the row and convert it to a [`Transaction`], parsing amounts stored as text according to the
+    /// `locale` and applying the `precision` policy to amounts with too many decimal places.
+    /// Deposits and withdrawals must have a positive amount, other transactions none
+    pub fn into_transaction(
+        self,
+        locale: Locale,
+        precision: Precision,
+    ) -> Result<Transaction<C, T>, AmountError> {
+        let kind = self.r#type.name();
+        let amount = || {
+            let amount = self.amount.as_ref().ok_or(AmountError::Missing(kind))?;
+            amount::validate(amount.value(locale)?, precision)
+        };
+
+        let operation = match self.r#type {
+            TransactionType::Deposit => TransactionOperation::Deposit(amount()?),
+            TransactionType::Withdrawal => TransactionOperation::Withdrawal(amount()?),
+
+            _ => {
+                if let Some(amount) = self.amount {
+                    return Err(AmountError::Unexpected { kind, amount });
+                }
+
+                match self.r#type {
+                    TransactionType::Dispute => TransactionOperation::Dispute,
+                    TransactionType::Resolve => TransactionOperation::Resolve,
+                    _ => TransactionOperation::Chargeback,
+                }
+            }
+        };
+
+        Ok(Transaction {
+            client: self.client.into(),
+            id: self.tx.into(),
+            operation,
+            batch: self.batch.map(Into::into),
+            provenance: None,
+        })
+    }
+}
+
+impl<C: Id, T: Id> TryInto<Transaction<C, T>> for TransactionRow<C, T> {
+    type Error = AmountError;
+
+    /// Convert the row with the default [`Locale`] and [`Precision`] policy, see [`TransactionRow::into_transaction`]
+    fn try_into(self) -> Result<Transaction<C, T>, Self::Error> {
+        self.into_transaction(Locale::default(), Precision::default())
+    }
+}
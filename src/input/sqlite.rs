@@ -0,0 +1,354 @@
+use std::{fmt::Write as _, path::Path, str::FromStr, sync::mpsc, thread};
+
+use anyhow::anyhow;
+use rusqlite::{types::ValueRef, Connection, OpenFlags};
+
+use crate::transaction::Id;
+
+use super::{
+    amount::{Amount, AmountError},
+    FieldError, ReadError, Reader, SourceRow, TransactionRow,
+};
+
+/// Query reading transactions when none is given
+pub const DEFAULT_QUERY: &str = "SELECT * FROM transactions";
+
+/// Magic bytes at the start of a SQLite database
+pub(super) const MAGIC: &[u8] = b"SQLite format 3\0";
+
+/// Number of rows read ahead of the rows being yielded
+const READ_AHEAD: usize = 1024;
+
+/// A [`Reader`] of transactions from the rows of a SQLite query
+///
+/// The query must return the `type`, `client` and `tx` columns and may return the `amount`, `batch` and `timestamp`
+/// columns of a [`TransactionRow`], in any order and along with other columns that are ignored. Columns can be renamed
+/// with `AS` and identifiers may be stored as integers or text. Amounts stored as text are parsed according to the
+/// locale when converted, see [`TransactionRow::into_transaction`]
+///
+/// The query runs on a dedicated thread which reads rows lazily, a bounded number of rows ahead of the rows being
+/// yielded
+pub struct SqliteReader<C = u16, T = u32> {
+    rows: Rows<C, T>,
+}
+
+impl<C: Id, T: Id> SqliteReader<C, T> {
+    /// Open the SQLite database at `path` read-only and run the `query`, see [`DEFAULT_QUERY`]
+    pub fn new(path: impl AsRef<Path>, query: &str) -> anyhow::Result<Self> {
+        let connection = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+
+        Self::from_connection(connection, query)
+    }
+
+    /// Run the `query` on the `connection`
+    /// Fails if the query is invalid or does not return the required columns
+    pub fn from_connection(connection: Connection, query: &str) -> anyhow::Result<Self> {
+        let query = query.to_string();
+        let (ready, started) = mpsc::sync_channel(1);
+        let (sender, receiver) = mpsc::sync_channel(READ_AHEAD);
+
+        let worker = thread::spawn(move || {
+            let mut statement = match connection.prepare(&query) {
+                Ok(statement) => statement,
+                Err(e) => return drop(ready.send(Err(e.into()))),
+            };
+            let columns = match Columns::new(&statement.column_names()) {
+                Ok(columns) => columns,
+                Err(e) => return drop(ready.send(Err(e))),
+            };
+            let mut rows = match statement.query([]) {
+                Ok(rows) => rows,
+                Err(e) => return drop(ready.send(Err(e.into()))),
+            };
+            if ready.send(Ok(())).is_err() {
+                return;
+            }
+
+            let mut line = 0;
+            loop {
+                line += 1;
+                let item = match rows.next() {
+                    Ok(Some(row)) => columns.read(row, line),
+                    Ok(None) => break,
+                    Err(e) => (
+                        SourceRow {
+                            line,
+                            ..Default::default()
+                        },
+                        Err(ReadError::read(e).locate(line, None)),
+                    ),
+                };
+
+                if sender.send(item).is_err() {
+                    break;
+                }
+            }
+        });
+
+        started
+            .recv()
+            .map_err(|_| anyhow!("the SQLite query thread panicked"))??;
+
+        Ok(Self {
+            rows: Rows {
+                receiver: Some(receiver),
+                worker: Some(worker),
+            },
+        })
+    }
+
+    /// Convert the reader to an iterator over the records along with the [`SourceRow`] they have been read from
+    /// The line of a row is its row number in the results of the query, starting at 1, and its text the values of
+    /// its columns separated by commas
+    pub fn into_rows(self) -> Rows<C, T> {
+        self.rows
+    }
+}
+
+/// Indices of the columns of a [`TransactionRow`] in the results of a query
+struct Columns {
+    kind: usize,
+    client: usize,
+    tx: usize,
+    amount: Option<usize>,
+    batch: Option<usize>,
+    timestamp: Option<usize>,
+
+    /// Number of columns returned by the query
+    len: usize,
+}
+
+impl Columns {
+    fn new(names: &[&str]) -> anyhow::Result<Self> {
+        let find = |column: &str| {
+            names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(column))
+        };
+        let require = |column: &str| {
+            find(column)
+                .ok_or_else(|| anyhow!("the SQLite query does not return a `{column}` column"))
+        };
+
+        Ok(Self {
+            kind: require("type")?,
+            client: require("client")?,
+            tx: require("tx")?,
+            amount: find("amount"),
+            batch: find("batch"),
+            timestamp: find("timestamp"),
+            len: names.len(),
+        })
+    }
+
+    /// Read the record of the `line`-th row of the results along with the [`SourceRow`] it has been read from
+    fn read<C: Id, T: Id>(&self, row: &rusqlite::Row, line: u64) -> Item<C, T> {
+        let mut text = String::new();
+        for index in 0..self.len {
+            if index > 0 {
+                text.push(',');
+            }
+            match row.get_ref(index) {
+                Ok(ValueRef::Null) | Err(_) => {}
+                Ok(ValueRef::Integer(value)) => write!(text, "{value}").unwrap(),
+                Ok(ValueRef::Real(value)) => write!(text, "{value}").unwrap(),
+                Ok(ValueRef::Text(value)) => text.push_str(&String::from_utf8_lossy(value)),
+                Ok(ValueRef::Blob(_)) => text.push_str("<blob>"),
+            }
+        }
+
+        let record = self.record(row).map_err(|e| e.locate(line, None));
+
+        (SourceRow::new(line, None, text), record)
+    }
+
+    fn record<C: Id, T: Id>(&self, row: &rusqlite::Row) -> Record<C, T> {
+        let value = |column: &str, index: Option<usize>| match index {
+            Some(index) => row.get_ref(index).map_err(|e| ReadError::field(column, e)),
+            None => Ok(ValueRef::Null),
+        };
+
+        let kind = match value("type", Some(self.kind))? {
+            ValueRef::Text(kind) => text("type", kind)?.trim().to_ascii_lowercase(),
+            other => return Err(unexpected("type", "text", other)),
+        };
+
+        let amount = match value("amount", self.amount)? {
+            ValueRef::Null => None,
+            ValueRef::Integer(amount) => Some(Amount::Number(amount as f64)),
+            ValueRef::Real(amount) => Some(Amount::Number(amount)),
+            ValueRef::Text(amount) => Some(Amount::Text(text("amount", amount)?.to_string())),
+            other => return Err(unexpected("amount", "a number or text", other)),
+        };
+
+        TransactionRow::from_columns(
+            &kind,
+            id(value("client", Some(self.client))?, "client")?,
+            id(value("tx", Some(self.tx))?, "tx")?,
+            amount,
+            integer(value("batch", self.batch)?, "batch")?,
+            integer(value("timestamp", self.timestamp)?, "timestamp")?,
+        )
+    }
+}
+
+/// Text of a value of the `column`
+fn text<'a>(column: &str, value: &'a [u8]) -> Result<&'a str, ReadError> {
+    std::str::from_utf8(value).map_err(|e| ReadError::field(column, e))
+}
+
+/// Error raised when a value of the `column` is not of the `expected` type
+fn unexpected(column: &str, expected: &'static str, value: ValueRef) -> ReadError {
+    let error = match value {
+        ValueRef::Null => FieldError::Missing,
+        _ => FieldError::Unexpected {
+            expected,
+            found: value.data_type().to_string(),
+        },
+    };
+
+    ReadError::field(column, error)
+}
+
+/// Parse an identifier stored as an integer or as text
+fn id<I: FromStr>(value: ValueRef, column: &str) -> Result<I, ReadError> {
+    let text = match value {
+        ValueRef::Integer(id) => id.to_string(),
+        ValueRef::Text(id) => text(column, id)?.trim().to_string(),
+        other => return Err(unexpected(column, "an integer or text", other)),
+    };
+
+    text.parse()
+        .map_err(|_| ReadError::field(column, FieldError::Invalid(text)))
+}
+
+/// Read an optional non-negative integer column
+fn integer(value: ValueRef, column: &str) -> Result<Option<u64>, ReadError> {
+    match value {
+        ValueRef::Null => Ok(None),
+        ValueRef::Integer(value) => value
+            .try_into()
+            .map(Some)
+            .map_err(|_| ReadError::field(column, FieldError::Invalid(value.to_string()))),
+        other => Err(unexpected(column, "an integer", other)),
+    }
+}
+
+type Record<C, T> = Result<TransactionRow<C, T>, ReadError>;
+
+type Item<C, T> = (SourceRow, Record<C, T>);
+
+/// Iterator over the records of a SQLite query along with the [`SourceRow`] they have been read from
+pub struct Rows<C, T> {
+    receiver: Option<mpsc::Receiver<Item<C, T>>>,
+    worker: Option<thread::JoinHandle<()>>,
+}
+
+impl<C: Id, T: Id> Iterator for Rows<C, T> {
+    type Item = Item<C, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.as_ref()?.recv().ok()
+    }
+}
+
+impl<C, T> Drop for Rows<C, T> {
+    fn drop(&mut self) {
+        // The query thread stops once it cannot send rows anymore
+        drop(self.receiver.take());
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<C: Id, T: Id> Reader<C, T> for SqliteReader<C, T> {
+    type IntoError = AmountError;
+    type Item = TransactionRow<C, T>;
+    type Error = ReadError;
+
+    type Iterator = std::iter::Map<Rows<C, T>, fn(Item<C, T>) -> Record<C, T>>;
+
+    fn into_iter(self) -> Self::Iterator {
+        self.rows.map(|(_, record)| record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        input::{Locale, Precision},
+        transaction::{Transaction, TransactionOperation},
+    };
+
+    use super::*;
+
+    #[test]
+    fn read_query_rows() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE ledger (id INTEGER PRIMARY KEY, kind TEXT, account TEXT, amount, note TEXT);
+                 INSERT INTO ledger (kind, account, amount, note) VALUES
+                     ('deposit', '1', 10, 'salary'),
+                     ('Withdrawal', 1, '2,50', NULL),
+                     ('dispute', 1, NULL, NULL),
+                     ('deposit', 'alice', 1.5, NULL),
+                     ('deposit', 2, 3.5, 'ignored');",
+            )
+            .unwrap();
+
+        let query = "SELECT kind AS type, account AS client, id AS tx, amount, note FROM ledger
+                     WHERE note IS NULL OR note <> 'ignored' ORDER BY id";
+        let rows = SqliteReader::<u16, u32>::from_connection(connection, query)
+            .unwrap()
+            .into_rows()
+            .map(|(row, record)| {
+                let transaction = record.and_then(|r| {
+                    r.into_transaction(Locale::Comma, Precision::Reject)
+                        .map_err(ReadError::invalid)
+                });
+                (row, transaction)
+            })
+            .collect::<Vec<(SourceRow, Result<Transaction, ReadError>)>>();
+
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[0].0.line, 1);
+        assert_eq!(rows[0].0.text(), "deposit,1,1,10,salary");
+        assert!(matches!(
+            rows[0].1.as_ref().map(|t| t.operation),
+            Ok(TransactionOperation::Deposit(amount)) if amount == 10.0
+        ));
+        assert!(matches!(
+            rows[1].1.as_ref().map(|t| t.operation),
+            Ok(TransactionOperation::Withdrawal(amount)) if amount == 2.5
+        ));
+        assert!(matches!(
+            rows[2].1.as_ref().map(|t| t.operation),
+            Ok(TransactionOperation::Dispute)
+        ));
+
+        // Rows with invalid identifiers are malformed
+        let error = rows[3].1.as_ref().unwrap_err();
+        assert_eq!(error.position().line, Some(4));
+        assert_eq!(error.position().field.as_deref(), Some("client"));
+        assert_eq!(
+            error.to_string(),
+            "line 4, field `client`: invalid value `alice`"
+        );
+
+        // Queries without the required columns are rejected upfront
+        let connection = Connection::open_in_memory().unwrap();
+        let error =
+            SqliteReader::<u16, u32>::from_connection(connection, "SELECT 1 AS type, 2 AS tx")
+                .err()
+                .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "the SQLite query does not return a `client` column"
+        );
+    }
+}
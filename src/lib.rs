@@ -0,0 +1,47 @@
+//! A toy payment engine processing deposits, withdrawals, disputes, resolves and chargebacks
+//! and maintaining the resulting client accounts.
+//!
+//! Transactions are read with an [`input::Reader`] such as the [`CsvReader`], processed by the
+//! [`TransactionEngine`] and the resulting accounts are written with an [`output::Writer`] such as the [`CsvWriter`].
+//!
+//! ```no_run
+//! use dab::{input, AccountOrder, CsvWriter, TransactionEngine, Writer};
+//!
+//! let mut engine = TransactionEngine::<u16, u32>::new();
+//! for transaction in input::read_csv("transactions.csv")? {
+//!     engine.process(transaction?);
+//! }
+//!
+//! let mut writer = CsvWriter::new(std::io::stdout())?;
+//! for account in engine.accounts(AccountOrder::Client) {
+//!     writer.write(account)?;
+//! }
+//! writer.into_inner()?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod input;
+pub mod output;
+pub mod reconcile;
+pub mod transaction;
+
+#[cfg(feature = "arrow")]
+pub use input::ArrowReader;
+#[cfg(feature = "camt")]
+pub use input::CamtReader;
+#[cfg(feature = "mmap")]
+pub use input::MmapCsvReader;
+#[cfg(feature = "parquet")]
+pub use input::ParquetReader;
+pub use input::{
+    BinaryReader, BinaryWriter, CsvReader, JsonLinesReader, ParallelCsvReader, Reader,
+    TransactionRow,
+};
+#[cfg(feature = "sqlite")]
+pub use output::SqliteWriter;
+pub use output::{CsvWriter, Writer};
+pub use transaction::{
+    engine::{AccountOrder, BatchError, Rejection, TransactionEngine},
+    Account, ClientId, Id, Provenance, Transaction, TransactionId, TransactionOperation,
+    TransactionRecord,
+};
@@ -1,32 +1,735 @@
-use std::io;
+use std::{
+    fs::File,
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-use anyhow::bail;
-use output::Writer;
-use transaction::engine::TransactionEngine;
+use anyhow::{anyhow, bail, Context};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+#[cfg(feature = "camt")]
+use dab::input::camt::{CamtOptions, ClientAccount, Extraction};
+use dab::{
+    input::{
+        self, binary::BinaryError, BinaryWriter, CsvDialect, FileOrder, Format, Locale, Precision,
+        ReadError, ReadOptions, SourceRow,
+    },
+    output::{self, ErrorPolicy, Report},
+    reconcile,
+    transaction::reorder::ReorderConfig,
+    Account, AccountOrder, Id, Transaction, TransactionEngine, TransactionOperation, Writer,
+};
 
-mod input;
-mod output;
-mod transaction;
+/// A toy payment engine that processes transactions from a CSV file and outputs the resulting accounts
+#[derive(Debug, Parser)]
+#[command(
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    /// Files or glob patterns of the files containing the transactions to process, `-` reads from the
+    /// standard input
+    #[arg(required = true)]
+    transactions_files: Vec<PathBuf>,
+
+    /// Order in which accounts are written: `client`, `total` or `status`
+    #[arg(long, default_value = "client")]
+    order: AccountOrder,
+
+    /// Upsert the accounts into a table of the SQLite database at this path, created if needed, instead of writing
+    /// them to the standard output
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "FILE")]
+    sqlite: Option<PathBuf>,
+
+    /// Table of the SQLite database receiving the accounts
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "NAME", default_value = output::sqlite::DEFAULT_TABLE, requires = "sqlite")]
+    table: String,
+
+    #[command(flatten)]
+    engine: EngineArgs,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+impl Cli {
+    /// Representation of identifiers selected for the command
+    fn ids(&self) -> Ids {
+        match &self.command {
+            Some(
+                Command::Query { engine, .. }
+                | Command::Reconcile { engine, .. }
+                | Command::Convert { engine, .. },
+            ) => engine.ids,
+            None => self.engine.ids,
+        }
+    }
+}
+
+/// Representation of client and transaction identifiers
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum Ids {
+    /// 16-bit client identifiers and 32-bit transaction identifiers
+    #[default]
+    Compact,
+
+    /// 64-bit client and transaction identifiers
+    Wide,
+
+    /// Opaque string client and transaction identifiers such as UUIDs
+    String,
+}
+
+/// Options controlling how the [`TransactionEngine`] processes transactions
+#[derive(Debug, Args)]
+struct EngineArgs {
+    /// Verify account invariants after every transaction and abort on the first violation
+    #[arg(long)]
+    verify: bool,
+
+    /// Park dispute, resolve and chargeback rows referencing a transaction that has not been seen yet
+    /// for at most this number of rows
+    #[arg(long, value_name = "ROWS")]
+    reorder_max_rows: Option<u64>,
+
+    /// Park dispute, resolve and chargeback rows referencing a transaction that has not been seen yet
+    /// for at most this number of milliseconds
+    #[arg(long, value_name = "MILLISECONDS")]
+    reorder_max_age: Option<u64>,
+
+    /// Representation of client and transaction identifiers
+    #[arg(long, value_enum, default_value_t)]
+    ids: Ids,
+
+    /// Format of the transactions files: `csv`, `jsonl`, `parquet`, `arrow`, `dab`, `camt` or `sqlite`. Detected from
+    /// the file extension, ignoring compression extensions, or else from the content of the file by default
+    #[arg(long)]
+    format: Option<Format>,
+
+    /// How amounts are written: `plain` numbers, `point` or `comma` decimal separator with thousands separators and
+    /// currencies, or `auto` to guess the decimal separator of every amount
+    #[arg(long, default_value = "plain")]
+    locale: Locale,
+
+    /// What to do with amounts that have more than four decimal places: `reject` the row or `round` the amount
+    #[arg(long, value_name = "POLICY", default_value = "reject")]
+    precision: Precision,
+
+    /// What to do when a malformed row is read: `fail` on the first one, `skip` it, or `collect` every malformed row
+    /// and fail once all the rows have been read
+    #[arg(long, value_name = "POLICY", default_value = "fail")]
+    on_error: ErrorPolicy,
+
+    /// CSV file receiving every malformed or rejected row along with its file, line and the reason it was
+    /// set aside
+    #[arg(long, value_name = "FILE")]
+    dead_letter: Option<PathBuf>,
+
+    /// Order in which multiple transactions files are processed: `name` keeps the order in which the files are given,
+    /// sorting the files matching a glob pattern by name, `timestamp` sorts them by their earliest timestamp
+    #[arg(long, default_value = "name")]
+    file_order: FileOrder,
+
+    /// Print the number of processed, rejected and malformed rows of every transactions file to the standard error
+    #[arg(long)]
+    stats: bool,
+
+    /// Number of threads parsing CSV files, which are parsed on the main thread by default
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    threads: usize,
+
+    /// Memory-map CSV files and parse them in place rather than streaming them, for uncompressed files with plain
+    /// amounts
+    #[cfg(feature = "mmap")]
+    #[arg(long, conflicts_with = "threads")]
+    mmap: bool,
+
+    /// Query reading the transactions of SQLite databases, returning the `type`, `client`, `tx` and optional
+    /// `amount`, `batch` and `timestamp` columns
+    #[cfg(feature = "sqlite")]
+    #[arg(long, value_name = "SQL", default_value = input::sqlite::DEFAULT_QUERY)]
+    query: String,
+
+    #[command(flatten)]
+    csv: CsvArgs,
+
+    #[cfg(feature = "camt")]
+    #[command(flatten)]
+    camt: CamtArgs,
+}
+
+/// Options describing the layout of CSV transactions files
+#[derive(Debug, Args)]
+#[command(next_help_heading = "CSV dialect")]
+struct CsvArgs {
+    /// Character separating the fields of a row, `\t` for tabulations
+    #[arg(long, value_name = "CHAR", default_value = ",", value_parser = parse_byte)]
+    delimiter: u8,
+
+    /// Character quoting fields
+    #[arg(long, value_name = "CHAR", default_value = "\"", value_parser = parse_byte)]
+    quote: u8,
+
+    /// Never treat quote characters as quoting fields
+    #[arg(long)]
+    no_quoting: bool,
+
+    /// Files have no header row and hold the `type`, `client`, `tx`, `amount`, `batch` and `timestamp` columns
+    /// in this order
+    #[arg(long)]
+    no_header: bool,
+
+    /// Read the column named `HEADER` in the header row as the `COLUMN` column, such as `kind=type`
+    #[arg(long = "column", value_name = "HEADER=COLUMN", value_parser = parse_pair)]
+    columns: Vec<(String, String)>,
+
+    /// Read the `ALIAS` transaction type as the `TYPE` transaction type, such as `credit=deposit`
+    #[arg(long = "type-alias", value_name = "ALIAS=TYPE", value_parser = parse_pair)]
+    aliases: Vec<(String, String)>,
+}
+
+impl CsvArgs {
+    fn dialect(&self) -> CsvDialect {
+        let mut dialect = CsvDialect::default();
+        dialect.delimiter = self.delimiter;
+        dialect.quote = (!self.no_quoting).then_some(self.quote);
+        dialect.has_headers = !self.no_header;
+        dialect.columns = self.columns.iter().cloned().collect();
+        dialect.aliases = self.aliases.iter().cloned().collect();
+
+        dialect
+    }
+}
+
+/// Options mapping the entries of camt.053 statements to transactions
+#[cfg(feature = "camt")]
+#[derive(Debug, Args)]
+#[command(next_help_heading = "camt.053 statements")]
+struct CamtArgs {
+    /// Account identifying the client of an entry: the `statement` account or the `counterparty` account, debtor of
+    /// credits and creditor of debits
+    #[arg(long, value_name = "ACCOUNT", default_value = "statement")]
+    camt_account: ClientAccount,
+
+    /// How the client identifier is extracted from the IBAN or other identifier of the account: `whole`, `digits`,
+    /// the `last:N` characters or the characters in `range:START..END`
+    #[arg(long, value_name = "EXTRACTION", default_value = "whole")]
+    camt_client: Extraction,
+
+    /// How the transaction identifier is extracted from the reference of the entry, see `--camt-client`
+    #[arg(long, value_name = "EXTRACTION", default_value = "whole")]
+    camt_tx: Extraction,
+}
+
+#[cfg(feature = "camt")]
+impl CamtArgs {
+    fn options(&self) -> CamtOptions {
+        let mut options = CamtOptions::default();
+        options.account = self.camt_account;
+        options.client = self.camt_client;
+        options.tx = self.camt_tx;
+
+        options
+    }
+}
+
+/// Parse a single ASCII character given on the command line
+fn parse_byte(s: &str) -> Result<u8, String> {
+    match s {
+        "\\t" => Ok(b'\t'),
+        _ => match s.as_bytes() {
+            [byte] if byte.is_ascii() => Ok(*byte),
+            _ => Err(format!("expected a single ASCII character, found `{s}`")),
+        },
+    }
+}
+
+/// Parse a `KEY=VALUE` pair given on the command line
+fn parse_pair(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected `KEY=VALUE`, found `{s}`"))
+}
+
+impl EngineArgs {
+    fn read_options(&self) -> ReadOptions {
+        let mut options = ReadOptions::default();
+        options.dialect = self.csv.dialect();
+        options.locale = self.locale;
+        options.precision = self.precision;
+        options.threads = self.threads;
+        #[cfg(feature = "mmap")]
+        {
+            options.mmap = self.mmap;
+        }
+        #[cfg(feature = "camt")]
+        {
+            options.camt = self.camt.options();
+        }
+        #[cfg(feature = "sqlite")]
+        {
+            options.query = Some(self.query.clone());
+        }
+
+        options
+    }
+
+    fn engine<C: Id, T: Id>(&self) -> TransactionEngine<C, T> {
+        let engine = TransactionEngine::new().with_verification(self.verify);
+
+        if self.reorder_max_rows.is_none() && self.reorder_max_age.is_none() {
+            return engine;
+        }
+
+        engine.with_reorder_buffer(ReorderConfig {
+            max_rows: self.reorder_max_rows,
+            max_age: self.reorder_max_age.map(Duration::from_millis),
+        })
+    }
+
+    /// Report of the malformed and rejected rows, written to the dead-letter file if one has been given
+    fn report<C: Id, T: Id>(&self) -> anyhow::Result<Report<C, T>> {
+        let dead_letter = self
+            .dead_letter
+            .as_deref()
+            .map(|path| {
+                File::create(path).with_context(|| format!("failed to create {}", path.display()))
+            })
+            .transpose()?;
+
+        Report::new(self.on_error, dead_letter)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Process transactions and query the resulting accounts and transactions
+    #[command(subcommand_precedence_over_arg = true)]
+    Query {
+        /// Files or glob patterns of the files containing the transactions to process, `-` reads from the
+        /// standard input
+        #[arg(required = true)]
+        transactions_files: Vec<PathBuf>,
+
+        #[command(flatten)]
+        engine: EngineArgs,
+
+        #[command(subcommand)]
+        query: Query,
+    },
+
+    /// Process transactions and reconcile the resulting accounts against expected accounts.
+    /// Differences are written to the standard output and the command fails if any is found
+    Reconcile {
+        /// Files or glob patterns of the files containing the transactions to process, `-` reads from the
+        /// standard input
+        #[arg(required = true)]
+        transactions_files: Vec<PathBuf>,
+
+        /// CSV file containing the expected accounts, with the same columns as the accounts output
+        expected_file: PathBuf,
+
+        /// Maximum absolute difference for funds to be considered equal
+        #[arg(long, default_value_t = 0.0001)]
+        tolerance: f64,
+
+        #[command(flatten)]
+        engine: EngineArgs,
+    },
+
+    /// Convert transactions to a dab-native binary file, which is faster to read than the other formats.
+    /// Malformed rows are handled like when processing transactions
+    Convert {
+        /// Files or glob patterns of the files containing the transactions to convert, `-` reads from the
+        /// standard input
+        #[arg(required = true)]
+        transactions_files: Vec<PathBuf>,
+
+        /// Binary file receiving the transactions of every file, in processing order
+        #[arg(long, short, value_name = "FILE")]
+        output: PathBuf,
+
+        #[command(flatten)]
+        engine: EngineArgs,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum Query {
+    /// Show the account of a client
+    Account { client: String },
+
+    /// List the deposits and withdrawals of a client along with their dispute state
+    Transactions { client: String },
+
+    /// Find a deposit or withdrawal of a client by its transaction id
+    Transaction { client: String, tx: String },
+
+    /// List the accounts that have at least one disputed transaction
+    Disputed,
+
+    /// List the accounts that have been locked following a chargeback
+    Locked,
+}
 
 fn main() -> anyhow::Result<()> {
-    let Some(transactions_file) = std::env::args().skip(1).next() else {
-        bail!("usage bail [transactions_file]");
+    let cli = Cli::parse();
+
+    match cli.ids() {
+        Ids::Compact => run_command::<u16, u32>(cli),
+        Ids::Wide => run_command::<u64, u64>(cli),
+        Ids::String => run_command::<String, String>(cli),
+    }
+}
+
+/// Run the command with client identifiers represented as `C` and transaction identifiers represented as `T`
+fn run_command<C: Id, T: Id>(cli: Cli) -> anyhow::Result<()> {
+    match cli.command {
+        Some(Command::Query {
+            transactions_files,
+            engine,
+            query,
+        }) => run_query::<C, T>(transactions_files, &engine, query),
+        Some(Command::Reconcile {
+            transactions_files,
+            expected_file,
+            tolerance,
+            engine,
+        }) => run_reconcile::<C, T>(transactions_files, expected_file, tolerance, &engine),
+        Some(Command::Convert {
+            transactions_files,
+            output,
+            engine,
+        }) => run_convert::<C, T>(transactions_files, &output, &engine),
+        None => run::<C, T>(cli),
+    }
+}
+
+/// Process all the transactions from `transactions_files` into a new [`TransactionEngine`]
+/// The state of the engine is carried over from one file to the next
+fn process<C: Id, T: Id>(
+    transactions_files: Vec<PathBuf>,
+    args: &EngineArgs,
+) -> anyhow::Result<TransactionEngine<C, T>> {
+    let options = args.read_options();
+    let files = input::files::<C, T>(transactions_files, args.format, args.file_order, &options)?;
+
+    let mut engine = args.engine();
+    let mut report = args.report()?;
+
+    for (path, format) in files {
+        report.open(&path);
+        process_file(&mut engine, &mut report, &path, format, &options)?;
+    }
+
+    engine.expire_parked();
+    report_parked(&mut engine, &mut report)?;
+
+    if args.stats {
+        print_statistics(&report);
+    }
+
+    report.finish()?;
+
+    Ok(engine)
+}
+
+/// Process all the transactions from the file at `path`
+fn process_file<C: Id, T: Id>(
+    engine: &mut TransactionEngine<C, T>,
+    report: &mut Report<C, T>,
+    path: &Path,
+    format: Format,
+    options: &ReadOptions,
+) -> anyhow::Result<()> {
+    let rows = input::read_rows(path, format, options)?;
+    let mut batch = Vec::new();
+
+    for (row, transaction) in rows {
+        report.read();
+
+        let transaction = match transaction {
+            Ok(transaction) => transaction,
+            Err(e) if is_corrupted(&e) => bail!("{}: {e}", path.display()),
+            Err(e) => {
+                report.malformed(row, e)?;
+                continue;
+            }
+        };
+
+        // Consecutive transactions with the same batch id form a batch that is applied all-or-nothing
+        if batch
+            .last()
+            .is_some_and(|(_, t): &(SourceRow, Transaction<C, T>)| t.batch != transaction.batch)
+        {
+            process_batch(engine, &mut batch, report)?;
+        }
+
+        match transaction.batch {
+            Some(_) => batch.push((row, transaction)),
+            None => {
+                let result = engine.try_process(transaction.clone());
+                report.processed(row, &transaction, result)?;
+            }
+        }
+
+        report_parked(engine, report)?;
+    }
+
+    // Batches do not span multiple files
+    process_batch(engine, &mut batch, report)?;
+    report_parked(engine, report)
+}
+
+/// Report the transactions released from the reorder buffer of the `engine` that have been rejected and the ones
+/// that expired in it
+fn report_parked<C: Id, T: Id>(
+    engine: &mut TransactionEngine<C, T>,
+    report: &mut Report<C, T>,
+) -> anyhow::Result<()> {
+    for (transaction, result) in engine.take_released() {
+        report.released(&transaction, result)?;
+    }
+
+    for transaction in engine.take_expired() {
+        let operation = match transaction.operation {
+            TransactionOperation::Dispute => "dispute",
+            TransactionOperation::Resolve => "resolve",
+            TransactionOperation::Chargeback => "chargeback",
+            _ => "transaction",
+        };
+
+        let reason = format!(
+            "{operation} of transaction {} for client {} expired before the transaction arrived",
+            transaction.id, transaction.client
+        );
+        report.expired(&transaction, reason)?;
+    }
+
+    Ok(())
+}
+
+/// Atomically process and clear a `batch` of transactions, reporting every row of the batch as rejected if it has
+/// been rolled back
+fn process_batch<C: Id, T: Id>(
+    engine: &mut TransactionEngine<C, T>,
+    batch: &mut Vec<(SourceRow, Transaction<C, T>)>,
+    report: &mut Report<C, T>,
+) -> anyhow::Result<()> {
+    let Some(id) = batch.first().and_then(|(_, t)| t.batch) else {
+        return Ok(());
     };
 
-    let transactions = input::read_csv(transactions_file)?;
+    let (rows, transactions): (Vec<_>, Vec<_>) = batch.drain(..).unzip();
 
-    let mut engine = TransactionEngine::new();
+    if let Err(e) = engine.process_batch(transactions) {
+        let reason = format!("batch {id} rolled back: {e}");
+        for row in rows {
+            report.rejected(row, reason.clone())?;
+        }
+    }
 
-    for transaction in transactions {
-        let transaction = transaction?;
-        engine.process(transaction);
+    Ok(())
+}
+
+/// Print the statistics of every transactions file of the `report` to the standard error
+fn print_statistics<C: Id, T: Id>(report: &Report<C, T>) {
+    for (file, statistics) in report.statistics() {
+        eprintln!(
+            "{file}: {} rows, {} processed, {} rejected, {} malformed",
+            statistics.rows,
+            statistics.processed(),
+            statistics.rejected,
+            statistics.malformed
+        );
+    }
+}
+
+fn run<C: Id, T: Id>(cli: Cli) -> anyhow::Result<()> {
+    let engine = process::<C, T>(cli.transactions_files, &cli.engine)?;
+
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = &cli.sqlite {
+        let mut writer = output::SqliteWriter::new(path, &cli.table)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+        for account in engine.accounts(cli.order) {
+            writer.write(account)?;
+        }
+        writer.into_inner()?;
+
+        return Ok(());
     }
 
+    write_accounts(engine.accounts(cli.order))
+}
+
+fn run_query<C: Id, T: Id>(
+    transactions_files: Vec<PathBuf>,
+    args: &EngineArgs,
+    query: Query,
+) -> anyhow::Result<()> {
+    let engine = process::<C, T>(transactions_files, args)?;
+
+    match query {
+        Query::Account { client } => {
+            let account = engine
+                .account(&parse_id::<C>(&client)?.into())
+                .ok_or_else(|| anyhow!("client {client} not found"))?;
+
+            write_accounts(std::iter::once(account))
+        }
+
+        Query::Transactions { client } => {
+            let mut transactions = engine
+                .transactions(&parse_id::<C>(&client)?.into())
+                .ok_or_else(|| anyhow!("client {client} not found"))?
+                .collect::<Vec<_>>();
+            transactions.sort_by(|lhs, rhs| lhs.id.cmp(&rhs.id));
+
+            let mut writer = output::CsvWriter::new(io::stdout())?;
+            for transaction in transactions {
+                writer.write_transaction(transaction)?;
+            }
+            writer.into_inner()?;
+
+            Ok(())
+        }
+
+        Query::Transaction { client, tx } => {
+            let transaction = engine
+                .transaction(&parse_id::<C>(&client)?.into(), &parse_id::<T>(&tx)?.into())
+                .ok_or_else(|| anyhow!("transaction {tx} of client {client} not found"))?;
+
+            let mut writer = output::CsvWriter::new(io::stdout())?;
+            writer.write_transaction(transaction)?;
+            writer.into_inner()?;
+
+            Ok(())
+        }
+
+        Query::Disputed => write_accounts(engine.disputed_accounts()),
+        Query::Locked => write_accounts(engine.locked_accounts()),
+    }
+}
+
+fn run_reconcile<C: Id, T: Id>(
+    transactions_files: Vec<PathBuf>,
+    expected_file: PathBuf,
+    tolerance: f64,
+    args: &EngineArgs,
+) -> anyhow::Result<()> {
+    let engine = process::<C, T>(transactions_files, args)?;
+    let expected = input::read_accounts_csv(expected_file)?.collect::<anyhow::Result<Vec<_>>>()?;
+
+    let differences = reconcile::reconcile(&engine, expected, tolerance);
+    let count = differences.len();
+
+    let mut writer = output::CsvWriter::new(io::stdout())?;
+    for difference in differences {
+        writer.write_difference(difference)?;
+    }
+    writer.into_inner()?;
+
+    if count > 0 {
+        bail!("found {count} differences with the expected accounts");
+    }
+
+    Ok(())
+}
+
+fn run_convert<C: Id, T: Id>(
+    transactions_files: Vec<PathBuf>,
+    output: &Path,
+    args: &EngineArgs,
+) -> anyhow::Result<()> {
+    let options = args.read_options();
+    let files = input::files::<C, T>(transactions_files, args.format, args.file_order, &options)?;
+
+    let file =
+        File::create(output).with_context(|| format!("failed to create {}", output.display()))?;
+
+    // A failed conversion leaves no partially written or corrupted output behind
+    if let Err(e) = convert::<C, T>(files, file, args, &options) {
+        std::fs::remove_file(output)
+            .with_context(|| format!("failed to remove {}", output.display()))?;
+        return Err(e);
+    }
+
+    Ok(())
+}
+
+/// Convert the transactions of `files` to a binary `file`
+fn convert<C: Id, T: Id>(
+    files: Vec<(PathBuf, Format)>,
+    file: File,
+    args: &EngineArgs,
+    options: &ReadOptions,
+) -> anyhow::Result<()> {
+    let mut writer = BinaryWriter::new(file)?;
+    let mut report = args.report::<C, T>()?;
+
+    for (path, format) in files {
+        report.open(&path);
+
+        for (row, record) in input::read_records::<C, T>(&path, format, options)? {
+            report.read();
+
+            let timestamp = record.as_ref().ok().and_then(|record| record.timestamp());
+            let transaction = record.and_then(|record| {
+                record
+                    .into_transaction(options.locale, options.precision)
+                    .map_err(|e| ReadError::invalid(e).at_line(row.line))
+            });
+
+            match transaction.map(|transaction| writer.write(&transaction, timestamp)) {
+                Ok(Ok(())) => {}
+                // Rows with identifiers that do not fit a binary file are malformed
+                Ok(Err(e @ BinaryError::NonInteger(_))) => {
+                    let error = ReadError::invalid(e).at_line(row.line);
+                    report.malformed(row, error)?
+                }
+                Ok(Err(e)) => return Err(e.into()),
+                Err(e) if is_corrupted(&e) => bail!("{}: {e}", path.display()),
+                Err(e) => report.malformed(row, e)?,
+            }
+        }
+    }
+
+    writer.into_inner()?;
+
+    if args.stats {
+        print_statistics(&report);
+    }
+
+    report.finish()
+}
+
+/// Whether a read `error` tells that the records of a binary file do not match its checksum, which is only known
+/// once they have all been read from the standard input. None of the transactions of the file can then be trusted
+/// so that no output is written, whatever the error policy
+fn is_corrupted(error: &ReadError) -> bool {
+    std::error::Error::source(error)
+        .and_then(|source| source.downcast_ref::<BinaryError>())
+        .is_some_and(|error| matches!(error, BinaryError::Checksum { .. }))
+}
+
+/// Parse an identifier given on the command line
+fn parse_id<I: FromStr>(id: &str) -> anyhow::Result<I> {
+    id.parse().map_err(|_| anyhow!("invalid identifier `{id}`"))
+}
+
+fn write_accounts<C: Id>(accounts: impl Iterator<Item = Account<C>>) -> anyhow::Result<()> {
     let mut writer = output::CsvWriter::new(io::stdout())?;
 
-    for account in engine.accounts() {
+    for account in accounts {
         writer.write(account)?;
     }
+    writer.into_inner()?;
 
     Ok(())
 }
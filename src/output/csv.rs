@@ -1,9 +1,15 @@
 use std::io;
 
-use crate::transaction::Account;
+use serde::Serialize;
 
-use super::Writer;
+use crate::{
+    reconcile::Difference,
+    transaction::{Account, ClientId, Id, TransactionId, TransactionKind, TransactionRecord},
+};
 
+use super::{DeadLetter, Writer};
+
+/// A [`Writer`] of accounts, transaction records, differences and dead letters as CSV rows
 pub struct CsvWriter<W>
 where
     W: io::Write,
@@ -26,15 +32,69 @@ where
 
         Ok(Self { writer })
     }
+
+    /// Write a [`TransactionRecord`] along with the `file`, `line` and `byte` columns of its provenance, which are
+    /// empty if the provenance is unknown
+    /// Accounts and transactions have different columns and should not be written with the same writer
+    pub fn write_transaction<C: Id, T: Id>(
+        &mut self,
+        transaction: TransactionRecord<C, T>,
+    ) -> Result<(), csv::Error> {
+        let provenance = transaction.provenance.as_ref();
+
+        self.writer.serialize(RecordRow {
+            client: &transaction.client,
+            id: &transaction.id,
+            kind: transaction.kind,
+            amount: transaction.amount,
+            disputed: transaction.disputed,
+            file: provenance.and_then(|p| p.file.as_deref()),
+            line: provenance.map(|p| p.line),
+            byte: provenance.and_then(|p| p.byte),
+        })
+    }
+
+    /// Write a reconciliation [`Difference`]
+    pub fn write_difference<C: Id>(&mut self, difference: Difference<C>) -> Result<(), csv::Error> {
+        self.writer.serialize(difference)
+    }
+
+    /// Write a [`DeadLetter`] row
+    pub fn write_dead_letter(&mut self, dead_letter: DeadLetter) -> Result<(), csv::Error> {
+        self.writer.serialize(dead_letter)
+    }
+
+    /// Flush the writer and return the underlying [`io::Write`]
+    pub fn into_inner(self) -> anyhow::Result<W> {
+        self.writer
+            .into_inner()
+            .map_err(|e| anyhow::anyhow!("{}", e.error()))
+    }
+}
+
+/// A [`TransactionRecord`] with its provenance flattened into columns, since CSV rows cannot nest structs
+#[derive(Serialize)]
+struct RecordRow<'a, C, T> {
+    client: &'a ClientId<C>,
+    #[serde(rename = "tx")]
+    id: &'a TransactionId<T>,
+    #[serde(rename = "type")]
+    kind: TransactionKind,
+    amount: f64,
+    disputed: bool,
+    file: Option<&'a str>,
+    line: Option<u64>,
+    byte: Option<u64>,
 }
 
-impl<W> Writer for CsvWriter<W>
+impl<W, C> Writer<C> for CsvWriter<W>
 where
     W: io::Write,
+    C: Id,
 {
     type Error = csv::Error;
 
-    fn write(&mut self, account: Account) -> Result<(), Self::Error> {
+    fn write(&mut self, account: Account<C>) -> Result<(), Self::Error> {
         self.writer.serialize(account)
     }
 }
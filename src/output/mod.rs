@@ -1,11 +1,40 @@
-use crate::transaction::Account;
+use serde::{Deserialize, Serialize};
+
+use crate::transaction::{Account, Id};
+
 pub mod csv;
+pub mod report;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
 pub use csv::CsvWriter;
+pub use report::{ErrorPolicy, Report, Statistics};
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteWriter;
 
-/// An abstraction to display or write accounts
-pub trait Writer {
+/// An abstraction to display or write accounts with client identifiers represented as `C`
+pub trait Writer<C: Id = u16> {
     /// Error type returned by the writer
     type Error: Into<Box<dyn std::error::Error>>;
 
-    fn write(&mut self, account: Account) -> Result<(), Self::Error>;
+    fn write(&mut self, account: Account<C>) -> Result<(), Self::Error>;
+}
+
+/// Represents an input row that has been set aside because it is malformed or has been rejected
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    /// Input that the row has been read from
+    pub file: String,
+
+    /// Line at which the row starts in the input
+    pub line: u64,
+
+    /// Offset in bytes at which the row starts in the input, for formats that tell it
+    pub byte: Option<u64>,
+
+    /// Why the row has been set aside
+    pub reason: String,
+
+    /// Original text of the row
+    pub text: String,
 }
@@ -0,0 +1,404 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io,
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::bail;
+use thiserror::Error;
+
+use crate::{
+    input::{ReadError, SourceRow},
+    transaction::{engine::Rejection, Account, ClientId, Id, Transaction, TransactionId},
+};
+
+use super::{CsvWriter, DeadLetter};
+
+/// What to do when a malformed row is read
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ErrorPolicy {
+    /// Abort on the first malformed row
+    #[default]
+    Fail,
+
+    /// Skip malformed rows
+    Skip,
+
+    /// Skip malformed rows and fail once all the rows have been read, reporting every malformed row
+    Collect,
+}
+
+/// Error raised when parsing an unknown [`ErrorPolicy`]
+#[derive(Debug, Error)]
+#[error("unknown error policy `{0}`, expected one of `fail`, `skip` or `collect`")]
+pub struct ParseErrorPolicyError(String);
+
+impl FromStr for ErrorPolicy {
+    type Err = ParseErrorPolicyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(ErrorPolicy::Fail),
+            "skip" => Ok(ErrorPolicy::Skip),
+            "collect" => Ok(ErrorPolicy::Collect),
+            _ => Err(ParseErrorPolicyError(s.to_string())),
+        }
+    }
+}
+
+/// Number of rows read from a transactions file by outcome
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Statistics {
+    /// Rows read from the file
+    pub rows: u64,
+
+    /// Rows that could not be read as a transaction
+    pub malformed: u64,
+
+    /// Transactions rejected by the engine, including rolled back batches and out-of-order disputes that expired or
+    /// were rejected once released
+    pub rejected: u64,
+}
+
+impl Statistics {
+    /// Rows that have been processed by the engine
+    pub fn processed(&self) -> u64 {
+        self.rows - self.malformed - self.rejected
+    }
+}
+
+/// Index of a transactions file of a [`Report`] and row of a transaction parked in the reorder buffer
+type ParkedRow = (usize, SourceRow);
+
+/// Tracks the malformed and rejected rows of every transactions file.
+/// Malformed rows are handled according to an [`ErrorPolicy`] and every malformed or rejected row is written
+/// as a [`DeadLetter`] to a CSV dead-letter file if one has been given
+pub struct Report<C: Id, T: Id, W: io::Write = File> {
+    policy: ErrorPolicy,
+
+    writer: Option<CsvWriter<W>>,
+
+    /// Every transactions file opened so far along with its statistics, the last one being processed
+    files: Vec<(String, Statistics)>,
+
+    /// Rows of the transactions parked in the reorder buffer of the engine along with the file they have
+    /// been read from, in arrival order
+    parked: HashMap<(ClientId<C>, TransactionId<T>), VecDeque<ParkedRow>>,
+
+    /// Malformed rows collected with the [`ErrorPolicy::Collect`] policy
+    collected: Vec<String>,
+}
+
+impl<C: Id, T: Id, W: io::Write> Report<C, T, W> {
+    /// Report malformed rows according to the `policy`, writing malformed and rejected rows to `dead_letter`
+    pub fn new(policy: ErrorPolicy, dead_letter: Option<W>) -> anyhow::Result<Self> {
+        let writer = dead_letter.map(CsvWriter::new).transpose()?;
+
+        Ok(Self {
+            policy,
+            writer,
+            files: Vec::new(),
+            parked: HashMap::new(),
+            collected: Vec::new(),
+        })
+    }
+
+    /// Start processing the transactions file at `path`
+    pub fn open(&mut self, path: &Path) {
+        self.files
+            .push((path.display().to_string(), Statistics::default()));
+    }
+
+    /// Index of the file being processed
+    fn current(&self) -> usize {
+        self.files.len() - 1
+    }
+
+    /// Count a row read from the file being processed
+    pub fn read(&mut self) {
+        let current = self.current();
+        self.files[current].1.rows += 1;
+    }
+
+    /// Set aside a malformed `row`, failing if the policy is [`ErrorPolicy::Fail`]
+    pub fn malformed(&mut self, row: SourceRow, error: ReadError) -> anyhow::Result<()> {
+        let current = self.current();
+        let (file, statistics) = &mut self.files[current];
+        statistics.malformed += 1;
+
+        // Read errors located at a line already tell it, along with the byte offset and field at fault
+        let message = match error.position().line {
+            Some(_) => format!("{file}: {error}"),
+            None => format!("{file}:{}: {error}", row.line),
+        };
+        self.write(current, row, error.to_string())?;
+
+        match self.policy {
+            ErrorPolicy::Fail => bail!("{message}"),
+            ErrorPolicy::Skip => {}
+            ErrorPolicy::Collect => self.collected.push(message),
+        }
+
+        Ok(())
+    }
+
+    /// Handle the `result` of processing the `transaction` read from `row`
+    pub fn processed(
+        &mut self,
+        row: SourceRow,
+        transaction: &Transaction<C, T>,
+        result: Result<Account<C>, Rejection<C, T>>,
+    ) -> anyhow::Result<()> {
+        match result {
+            Ok(_) => Ok(()),
+            Err(Rejection::Parked(_)) => {
+                let key = (transaction.client.clone(), transaction.id.clone());
+                let current = self.current();
+                self.parked
+                    .entry(key)
+                    .or_default()
+                    .push_back((current, row));
+                Ok(())
+            }
+            Err(rejection) => self.reject(self.current(), row, rejection.to_string()),
+        }
+    }
+
+    /// Handle the `result` of applying a parked `transaction` released from the reorder buffer, setting its row
+    /// aside if it has been rejected
+    pub fn released(
+        &mut self,
+        transaction: &Transaction<C, T>,
+        result: Result<Account<C>, Rejection<C, T>>,
+    ) -> anyhow::Result<()> {
+        match (self.unpark(transaction), result) {
+            (Some((file, row)), Err(rejection)) => self.reject(file, row, rejection.to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Set aside the row of a parked `transaction` that expired
+    pub fn expired(
+        &mut self,
+        transaction: &Transaction<C, T>,
+        reason: String,
+    ) -> anyhow::Result<()> {
+        match self.unpark(transaction) {
+            Some((file, row)) => self.reject(file, row, reason),
+            None => Ok(()),
+        }
+    }
+
+    /// Take the row of a parked `transaction`, transactions waiting for the same transaction leaving the reorder
+    /// buffer in arrival order
+    fn unpark(&mut self, transaction: &Transaction<C, T>) -> Option<ParkedRow> {
+        let key = (transaction.client.clone(), transaction.id.clone());
+        let rows = self.parked.get_mut(&key)?;
+
+        let row = rows.pop_front();
+        if rows.is_empty() {
+            self.parked.remove(&key);
+        }
+
+        row
+    }
+
+    /// Set aside a `row` of the file being processed rejected by the engine, such as a row of a rolled back batch
+    pub fn rejected(&mut self, row: SourceRow, reason: String) -> anyhow::Result<()> {
+        self.reject(self.current(), row, reason)
+    }
+
+    /// Set aside a `row` of the file at index `file` rejected by the engine
+    fn reject(&mut self, file: usize, row: SourceRow, reason: String) -> anyhow::Result<()> {
+        self.files[file].1.rejected += 1;
+        self.write(file, row, reason)
+    }
+
+    fn write(&mut self, file: usize, row: SourceRow, reason: String) -> anyhow::Result<()> {
+        if let Some(writer) = &mut self.writer {
+            writer.write_dead_letter(DeadLetter {
+                file: self.files[file].0.clone(),
+                line: row.line,
+                byte: row.byte,
+                reason,
+                text: row.text().into_owned(),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Statistics of every transactions file opened so far, in the order they have been opened
+    pub fn statistics(&self) -> impl Iterator<Item = (&str, &Statistics)> {
+        self.files
+            .iter()
+            .map(|(file, statistics)| (file.as_str(), statistics))
+    }
+
+    /// Flush the dead-letter file and fail if malformed rows have been collected
+    pub fn finish(self) -> anyhow::Result<()> {
+        if let Some(writer) = self.writer {
+            writer.into_inner()?;
+        }
+
+        if !self.collected.is_empty() {
+            bail!(
+                "found {} malformed rows:\n{}",
+                self.collected.len(),
+                self.collected.join("\n")
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{input::CsvReader, transaction::TransactionOperation, TransactionEngine};
+
+    use super::*;
+
+    const CSV: &str = "type,client,tx,amount\n\
+        deposit,1,1,1.0\n\
+        deposit,one,2,1.0\n\
+        withdrawal,1,3,5.0\n\
+        deposit,1,4\n";
+
+    /// Process the rows of [`CSV`] until the report fails
+    /// Returns the number of rows read, the result of the report and the dead letters
+    fn report(policy: ErrorPolicy) -> (u64, anyhow::Result<()>, Vec<DeadLetter>) {
+        let mut engine = TransactionEngine::<u16, u32>::new();
+        let mut dead_letter = Vec::new();
+        let mut report = Report::new(policy, Some(&mut dead_letter)).unwrap();
+        report.open(Path::new("transactions.csv"));
+
+        let mut process = || -> anyhow::Result<()> {
+            let reader = CsvReader::<u16, u32, &[u8]>::from_reader(CSV.as_bytes())?;
+            for (row, record) in reader.into_rows() {
+                report.read();
+                let transaction: Result<Transaction, _> =
+                    record.and_then(|r| r.try_into().map_err(ReadError::invalid));
+                match transaction {
+                    Ok(transaction) => {
+                        let result = engine.try_process(transaction.clone());
+                        report.processed(row, &transaction, result)?;
+                    }
+                    Err(e) => {
+                        let line = row.line;
+                        report.malformed(row, e.at_line(line))?;
+                    }
+                }
+            }
+            Ok(())
+        };
+        let result = process();
+
+        let rows = report.statistics().map(|(_, s)| s.rows).sum();
+        let result = result.and_then(|()| report.finish());
+        let dead_letters = csv::Reader::from_reader(dead_letter.as_slice())
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        (rows, result, dead_letters)
+    }
+
+    #[test]
+    fn fail_on_first_malformed_row() {
+        let (rows, result, _) = report(ErrorPolicy::Fail);
+
+        assert_eq!(rows, 2);
+        let error = result.unwrap_err().to_string();
+        assert!(error.starts_with("transactions.csv: line 3"), "{error}");
+    }
+
+    #[test]
+    fn skip_malformed_rows() {
+        let (rows, result, dead_letters) = report(ErrorPolicy::Skip);
+
+        assert_eq!(rows, 4);
+        assert!(result.is_ok());
+        assert_eq!(dead_letters.len(), 3);
+    }
+
+    #[test]
+    fn collect_malformed_rows() {
+        let (rows, result, dead_letters) = report(ErrorPolicy::Collect);
+
+        assert_eq!(rows, 4);
+        let error = result.unwrap_err().to_string();
+        assert!(error.starts_with("found 2 malformed rows:\n"), "{error}");
+
+        // Malformed and rejected rows are written along with their file, line, byte offset, reason and text
+        let positions = dead_letters
+            .iter()
+            .map(|d| (d.file.as_str(), d.line, d.byte))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            [
+                ("transactions.csv", 3, Some(38)),
+                ("transactions.csv", 4, Some(56)),
+                ("transactions.csv", 5, Some(75))
+            ]
+        );
+        assert_eq!(dead_letters[0].text, "deposit,one,2,1.0");
+        assert!(
+            dead_letters[0].reason.contains("line 3"),
+            "{:?}",
+            dead_letters[0]
+        );
+        assert_eq!(
+            dead_letters[1].reason,
+            "insufficient funds for withdrawal 3"
+        );
+        assert_eq!(dead_letters[2].text, "deposit,1,4");
+    }
+
+    #[test]
+    fn reject_released_transactions() {
+        let mut dead_letter = Vec::new();
+        let mut report =
+            Report::<u16, u32, _>::new(ErrorPolicy::Fail, Some(&mut dead_letter)).unwrap();
+        report.open(Path::new("transactions.csv"));
+
+        // Two disputes of the same deposit are parked, the second one being rejected once released
+        let dispute = Transaction::new(1.into(), 1.into(), TransactionOperation::Dispute);
+        for line in [2, 3] {
+            let row = SourceRow::new(line, None, format!("dispute,1,1,{line}"));
+            report.read();
+            report
+                .processed(row, &dispute, Err(Rejection::Parked(1.into())))
+                .unwrap();
+        }
+
+        let account = Account {
+            client: 1.into(),
+            available: 0.0,
+            held: 1.0,
+            total: 1.0,
+            locked: false,
+        };
+        report.released(&dispute, Ok(account)).unwrap();
+        report
+            .released(&dispute, Err(Rejection::NotDisputable(1.into())))
+            .unwrap();
+
+        let statistics = report.statistics().map(|(_, s)| *s).collect::<Vec<_>>();
+        assert_eq!((statistics[0].rows, statistics[0].rejected), (2, 1));
+        report.finish().unwrap();
+
+        let dead_letters: Vec<DeadLetter> = csv::Reader::from_reader(dead_letter.as_slice())
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].line, 3);
+        assert_eq!(dead_letters[0].reason, "transaction 1 cannot be disputed");
+    }
+}
@@ -0,0 +1,168 @@
+use std::path::Path;
+
+use rusqlite::{params, types::Value, Connection};
+
+use crate::transaction::{Account, Id};
+
+use super::Writer;
+
+/// Table receiving the accounts when none is given
+pub const DEFAULT_TABLE: &str = "accounts";
+
+/// A [`Writer`] of accounts that upserts them into a table of a SQLite database
+///
+/// The table is created if it does not exist, with a `client` primary key and the `available`, `held`, `total` and
+/// `locked` columns. The account of a client that is already in the table is updated. Every account is written within
+/// a single database transaction, which is committed by [`SqliteWriter::into_inner`]
+pub struct SqliteWriter {
+    connection: Connection,
+    upsert: String,
+}
+
+impl SqliteWriter {
+    /// Open or create the SQLite database at `path` and write accounts to its `table`, see [`DEFAULT_TABLE`]
+    pub fn new(path: impl AsRef<Path>, table: &str) -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open(path)?, table)
+    }
+
+    /// Write accounts to the `table` of the database of `connection`
+    pub fn from_connection(connection: Connection, table: &str) -> rusqlite::Result<Self> {
+        let table = format!("\"{}\"", table.replace('"', "\"\""));
+
+        connection.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {table} (
+                 client PRIMARY KEY NOT NULL,
+                 available REAL NOT NULL,
+                 held REAL NOT NULL,
+                 total REAL NOT NULL,
+                 locked INTEGER NOT NULL
+             );
+             BEGIN;"
+        ))?;
+
+        let upsert = format!(
+            "INSERT INTO {table} (client, available, held, total, locked) VALUES (?1, ?2, ?3, ?4, ?5)
+             ON CONFLICT (client) DO UPDATE SET
+                 available = excluded.available,
+                 held = excluded.held,
+                 total = excluded.total,
+                 locked = excluded.locked"
+        );
+
+        Ok(Self { connection, upsert })
+    }
+
+    /// Commit the accounts written so far and return the underlying [`Connection`]
+    pub fn into_inner(self) -> rusqlite::Result<Connection> {
+        self.connection.execute_batch("COMMIT")?;
+        Ok(self.connection)
+    }
+}
+
+impl<C: Id> Writer<C> for SqliteWriter {
+    type Error = rusqlite::Error;
+
+    fn write(&mut self, account: Account<C>) -> Result<(), Self::Error> {
+        // Integer identifiers are stored as integers so that they are ordered as such
+        let client = match account.client.get().as_i64() {
+            Some(client) => Value::Integer(client),
+            None => Value::Text(account.client.to_string()),
+        };
+
+        self.connection
+            .prepare_cached(&self.upsert)?
+            .execute(params![
+                client,
+                account.available,
+                account.held,
+                account.total,
+                account.locked,
+            ])?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ClientId;
+
+    use super::*;
+
+    fn account(client: u16, available: f64, locked: bool) -> Account {
+        Account {
+            client: ClientId::from(client),
+            available,
+            held: 0.0,
+            total: available,
+            locked,
+        }
+    }
+
+    #[test]
+    fn upsert_accounts() {
+        let mut writer =
+            SqliteWriter::from_connection(Connection::open_in_memory().unwrap(), "my \"accounts\"")
+                .unwrap();
+        writer.write(account(1, 1.5, false)).unwrap();
+        writer.write(account(2, 2.0, false)).unwrap();
+        let connection = writer.into_inner().unwrap();
+
+        let mut writer = SqliteWriter::from_connection(connection, "my \"accounts\"").unwrap();
+        writer.write(account(2, 0.5, true)).unwrap();
+        let connection = writer.into_inner().unwrap();
+
+        let accounts = connection
+            .prepare("SELECT client, available, total, locked FROM \"my \"\"accounts\"\"\" ORDER BY client")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, f64>(1)?,
+                    row.get::<_, f64>(2)?,
+                    row.get::<_, bool>(3)?,
+                ))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(accounts, [(1, 1.5, 1.5, false), (2, 0.5, 0.5, true)]);
+    }
+    #[test]
+    fn keep_text_identifiers() {
+        let mut writer =
+            SqliteWriter::from_connection(Connection::open_in_memory().unwrap(), DEFAULT_TABLE)
+                .unwrap();
+        for client in ["007", "7"] {
+            let account = Account {
+                client: ClientId::from(client.to_string()),
+                available: 1.0,
+                held: 0.0,
+                total: 1.0,
+                locked: false,
+            };
+            writer.write(account).unwrap();
+        }
+        let connection = writer.into_inner().unwrap();
+
+        // Both clients are kept apart as text instead of colliding as the integer 7
+        let clients = connection
+            .prepare("SELECT client, typeof(client) FROM accounts ORDER BY client")
+            .unwrap()
+            .query_map([], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            clients,
+            [
+                ("007".to_string(), "text".to_string()),
+                ("7".to_string(), "text".to_string())
+            ]
+        );
+    }
+}
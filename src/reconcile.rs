@@ -0,0 +1,216 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::transaction::{
+    engine::{AccountOrder, TransactionEngine},
+    Account, ClientId, Id,
+};
+
+/// Kind of a difference between an expected account and the account computed by the engine
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum DifferenceKind {
+    /// The client is expected but does not exist in the engine
+    Missing,
+
+    /// The client exists in the engine but is not expected
+    Unexpected,
+
+    /// The client is listed more than once in the expected accounts. Only its first account is compared
+    Duplicate,
+
+    /// The available funds do not match
+    Available,
+
+    /// The held funds do not match
+    Held,
+
+    /// The total funds do not match
+    Total,
+
+    /// The locked status does not match
+    Locked,
+}
+
+/// Represents a difference between an expected account and the account computed by the engine
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Difference<C = u16> {
+    /// Client that this difference is associated with
+    pub client: ClientId<C>,
+
+    /// What differs for the client
+    #[serde(rename = "difference")]
+    pub kind: DifferenceKind,
+
+    /// The expected value or [`None`] if the difference is not about a value of the account, such as a missing,
+    /// unexpected or duplicate client
+    pub expected: Option<String>,
+
+    /// The value computed by the engine or [`None`] if the difference is not about a value of the account
+    pub actual: Option<String>,
+}
+
+/// Reconcile the accounts of the `engine` against `expected` accounts
+/// Funds are considered equal if they do not differ by more than `tolerance`
+/// Returns the differences ordered by client identifier
+pub fn reconcile<C: Id, T: Id>(
+    engine: &TransactionEngine<C, T>,
+    expected: impl IntoIterator<Item = Account<C>>,
+    tolerance: f64,
+) -> Vec<Difference<C>> {
+    let mut differences = Vec::new();
+    let mut clients = HashSet::new();
+
+    for expected in expected {
+        if !clients.insert(expected.client.clone()) {
+            differences.push(Difference {
+                client: expected.client,
+                kind: DifferenceKind::Duplicate,
+                expected: None,
+                actual: None,
+            });
+            continue;
+        }
+
+        let Some(actual) = engine.account(&expected.client) else {
+            differences.push(Difference {
+                client: expected.client,
+                kind: DifferenceKind::Missing,
+                expected: None,
+                actual: None,
+            });
+            continue;
+        };
+
+        let funds = [
+            (
+                DifferenceKind::Available,
+                expected.available,
+                actual.available,
+            ),
+            (DifferenceKind::Held, expected.held, actual.held),
+            (DifferenceKind::Total, expected.total, actual.total),
+        ];
+
+        for (kind, expected_funds, actual_funds) in funds {
+            if (expected_funds - actual_funds).abs() > tolerance {
+                differences.push(Difference {
+                    client: expected.client.clone(),
+                    kind,
+                    expected: Some(expected_funds.to_string()),
+                    actual: Some(actual_funds.to_string()),
+                });
+            }
+        }
+
+        if expected.locked != actual.locked {
+            differences.push(Difference {
+                client: expected.client,
+                kind: DifferenceKind::Locked,
+                expected: Some(expected.locked.to_string()),
+                actual: Some(actual.locked.to_string()),
+            });
+        }
+    }
+
+    for account in engine.accounts(AccountOrder::Client) {
+        if !clients.contains(&account.client) {
+            differences.push(Difference {
+                client: account.client,
+                kind: DifferenceKind::Unexpected,
+                expected: None,
+                actual: None,
+            });
+        }
+    }
+
+    differences.sort_by(|lhs, rhs| lhs.client.cmp(&rhs.client));
+    differences
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::{Transaction, TransactionOperation};
+
+    const BOB: u16 = 1;
+    const ALICE: u16 = 2;
+    const EVE: u16 = 3;
+
+    fn account(client: u16, available: f64, held: f64, locked: bool) -> Account {
+        Account {
+            client: client.into(),
+            available,
+            held,
+            total: available + held,
+            locked,
+        }
+    }
+
+    fn engine() -> TransactionEngine {
+        let mut engine = TransactionEngine::new();
+
+        for (id, client) in [BOB, ALICE].into_iter().enumerate() {
+            engine.process(Transaction::new(
+                client.into(),
+                (id as u32).into(),
+                TransactionOperation::Deposit(100.0),
+            ));
+        }
+
+        engine
+    }
+
+    #[test]
+    fn reconcile_matching_accounts() {
+        let engine = engine();
+
+        // Make sure differences within the tolerance are ignored
+        let expected = [
+            account(BOB, 100.0, 0.0, false),
+            account(ALICE, 100.00001, 0.0, false),
+        ];
+
+        assert!(reconcile(&engine, expected, 0.0001).is_empty());
+    }
+
+    #[test]
+    fn reconcile_differences() {
+        let engine = engine();
+
+        // Alice is missing from the expected accounts, Eve does not exist in the engine and Bob is listed twice
+        let expected = [
+            account(EVE, 10.0, 0.0, false),
+            account(BOB, 90.0, 10.0, true),
+            account(BOB, 100.0, 0.0, false),
+        ];
+
+        let differences = reconcile(&engine, expected, 0.0001);
+        let kinds = differences
+            .iter()
+            .map(|d| (d.client, d.kind))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            kinds,
+            [
+                (BOB.into(), DifferenceKind::Available),
+                (BOB.into(), DifferenceKind::Held),
+                (BOB.into(), DifferenceKind::Locked),
+                (BOB.into(), DifferenceKind::Duplicate),
+                (ALICE.into(), DifferenceKind::Unexpected),
+                (EVE.into(), DifferenceKind::Missing),
+            ]
+        );
+
+        assert_eq!(differences[0].expected.as_deref(), Some("90"));
+        assert_eq!(differences[0].actual.as_deref(), Some("100"));
+
+        // Only differences between values of an account have an expected and an actual value
+        assert!(differences[3..]
+            .iter()
+            .all(|d| d.expected.is_none() && d.actual.is_none()));
+    }
+}
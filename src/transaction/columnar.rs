@@ -0,0 +1,328 @@
+use arrow_array::{
+    cast::AsArray,
+    types::{Float64Type, Int64Type, UInt16Type, UInt32Type, UInt64Type},
+    Array, ArrayRef, Float64Array, Int64Array, RecordBatch, StringArray, UInt16Array, UInt32Array,
+    UInt64Array,
+};
+use arrow_schema::{ArrowError, DataType};
+use serde::de::IntoDeserializer;
+use thiserror::Error;
+
+use crate::input::{
+    amount::{Amount, Locale, Precision},
+    FieldError, ReadError, TransactionRow,
+};
+
+use super::{Id, Provenance, Transaction};
+
+/// Error raised when reading the transactions of an Arrow [`RecordBatch`]
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum RecordBatchError {
+    /// A required column is missing
+    #[error("missing column `{0}`")]
+    MissingColumn(&'static str),
+
+    /// A column cannot be read as the values it should hold
+    #[error("invalid column `{name}`: {source}")]
+    InvalidColumn {
+        name: &'static str,
+        source: ArrowError,
+    },
+
+    /// A row is not a valid transaction, located at its row number starting at 1
+    #[error(transparent)]
+    InvalidRow(ReadError),
+}
+
+/// Values of the `type` column
+enum Kinds {
+    /// Type of every row
+    Names(StringArray),
+
+    /// Dictionary-encoded types
+    Dictionary {
+        column: ArrayRef,
+        keys: Vec<usize>,
+        names: StringArray,
+    },
+}
+
+impl Kinds {
+    fn new(column: &ArrayRef) -> Result<Self, ArrowError> {
+        let Some(dictionary) = column.as_any_dictionary_opt() else {
+            return Ok(Kinds::Names(text(column)?));
+        };
+
+        Ok(Kinds::Dictionary {
+            column: column.clone(),
+            keys: dictionary.normalized_keys(),
+            names: text(dictionary.values())?,
+        })
+    }
+
+    fn get(&self, index: usize) -> Option<&str> {
+        match self {
+            Kinds::Names(names) => names.is_valid(index).then(|| names.value(index)),
+            Kinds::Dictionary {
+                column,
+                keys,
+                names,
+            } => column.is_valid(index).then(|| names.value(keys[index])),
+        }
+    }
+}
+
+/// Values of an identifier column, read without going through text for integer columns
+enum Ids {
+    UInt16(UInt16Array),
+    UInt32(UInt32Array),
+    UInt64(UInt64Array),
+    Int64(Int64Array),
+    Text(StringArray),
+}
+
+impl Ids {
+    fn new(column: &ArrayRef) -> Result<Self, ArrowError> {
+        Ok(match column.data_type() {
+            DataType::UInt8 | DataType::UInt16 => Ids::UInt16(
+                cast(column, DataType::UInt16)?
+                    .as_primitive::<UInt16Type>()
+                    .clone(),
+            ),
+            DataType::UInt32 => Ids::UInt32(column.as_primitive::<UInt32Type>().clone()),
+            DataType::UInt64 => Ids::UInt64(column.as_primitive::<UInt64Type>().clone()),
+            DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => Ids::Int64(
+                cast(column, DataType::Int64)?
+                    .as_primitive::<Int64Type>()
+                    .clone(),
+            ),
+            _ => Ids::Text(text(column)?),
+        })
+    }
+
+    fn is_null(&self, index: usize) -> bool {
+        match self {
+            Ids::UInt16(column) => column.is_null(index),
+            Ids::UInt32(column) => column.is_null(index),
+            Ids::UInt64(column) => column.is_null(index),
+            Ids::Int64(column) => column.is_null(index),
+            Ids::Text(column) => column.is_null(index),
+        }
+    }
+
+    /// Identifier of the column `name` at `index`
+    fn get<I: Id>(&self, name: &'static str, index: usize) -> Result<I, ReadError> {
+        if self.is_null(index) {
+            return Err(ReadError::field(name, FieldError::Missing));
+        }
+
+        match self {
+            Ids::UInt16(column) => integer(name, column.value(index)),
+            Ids::UInt32(column) => integer(name, column.value(index)),
+            Ids::UInt64(column) => integer(name, column.value(index)),
+            Ids::Int64(column) => integer(name, column.value(index)),
+            Ids::Text(column) => {
+                let value = column.value(index);
+                value
+                    .parse()
+                    .map_err(|_| ReadError::field(name, FieldError::Invalid(value.to_string())))
+            }
+        }
+    }
+
+    fn text(&self, index: usize) -> String {
+        if self.is_null(index) {
+            return String::new();
+        }
+
+        match self {
+            Ids::UInt16(column) => column.value(index).to_string(),
+            Ids::UInt32(column) => column.value(index).to_string(),
+            Ids::UInt64(column) => column.value(index).to_string(),
+            Ids::Int64(column) => column.value(index).to_string(),
+            Ids::Text(column) => column.value(index).to_string(),
+        }
+    }
+}
+
+/// Convert an integer of the column `name` to an identifier, directly for integer identifiers and through its text
+/// otherwise
+fn integer<V, I>(name: &'static str, value: V) -> Result<I, ReadError>
+where
+    V: IntoDeserializer<'static, serde::de::value::Error> + Copy + ToString,
+    I: Id,
+{
+    I::deserialize(value.into_deserializer())
+        .ok()
+        .or_else(|| value.to_string().parse().ok())
+        .ok_or_else(|| ReadError::field(name, FieldError::Invalid(value.to_string())))
+}
+
+/// Values of the `amount` column
+enum Amounts {
+    Numbers(Float64Array),
+    Text(StringArray),
+}
+
+impl Amounts {
+    fn new(column: &ArrayRef) -> Result<Self, ArrowError> {
+        Ok(match column.data_type() {
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => {
+                Amounts::Text(text(column)?)
+            }
+            DataType::Float64 => Amounts::Numbers(column.as_primitive::<Float64Type>().clone()),
+            _ => Amounts::Numbers(
+                cast(column, DataType::Float64)?
+                    .as_primitive::<Float64Type>()
+                    .clone(),
+            ),
+        })
+    }
+
+    fn get(&self, index: usize) -> Option<Amount> {
+        match self {
+            Amounts::Numbers(column) => column
+                .is_valid(index)
+                .then(|| Amount::Number(column.value(index))),
+            Amounts::Text(column) => column
+                .is_valid(index)
+                .then(|| Amount::Text(column.value(index).to_string())),
+        }
+    }
+}
+
+/// Columns of a [`RecordBatch`] of transactions with `type`, `client`, `tx`, `amount` and optional `batch` and
+/// `timestamp` columns, read as typed arrays once for the whole record batch
+pub(crate) struct Columns {
+    kind: Kinds,
+    client: Ids,
+    tx: Ids,
+    amount: Option<Amounts>,
+    batch: Option<UInt64Array>,
+    timestamp: Option<UInt64Array>,
+    rows: usize,
+}
+
+impl Columns {
+    pub(crate) fn new(batch: &RecordBatch) -> Result<Self, RecordBatchError> {
+        fn column<V>(
+            batch: &RecordBatch,
+            name: &'static str,
+            read: impl FnOnce(&ArrayRef) -> Result<V, ArrowError>,
+        ) -> Result<Option<V>, RecordBatchError> {
+            batch
+                .column_by_name(name)
+                .map(|column| {
+                    read(column).map_err(|source| RecordBatchError::InvalidColumn { name, source })
+                })
+                .transpose()
+        }
+        fn required<V>(
+            batch: &RecordBatch,
+            name: &'static str,
+            read: impl FnOnce(&ArrayRef) -> Result<V, ArrowError>,
+        ) -> Result<V, RecordBatchError> {
+            column(batch, name, read)?.ok_or(RecordBatchError::MissingColumn(name))
+        }
+        let integers = |column: &ArrayRef| match column.data_type() {
+            DataType::UInt64 => Ok(column.as_primitive::<UInt64Type>().clone()),
+            _ => Ok(cast(column, DataType::UInt64)?
+                .as_primitive::<UInt64Type>()
+                .clone()),
+        };
+
+        Ok(Self {
+            kind: required(batch, "type", Kinds::new)?,
+            client: required(batch, "client", Ids::new)?,
+            tx: required(batch, "tx", Ids::new)?,
+            amount: column(batch, "amount", Amounts::new)?,
+            batch: column(batch, "batch", integers)?,
+            timestamp: column(batch, "timestamp", integers)?,
+            rows: batch.num_rows(),
+        })
+    }
+
+    /// Number of rows of the record batch
+    pub(crate) fn len(&self) -> usize {
+        self.rows
+    }
+
+    fn amount(&self, index: usize) -> Option<Amount> {
+        self.amount.as_ref().and_then(|amounts| amounts.get(index))
+    }
+
+    /// Read the row at `index` as a [`TransactionRow`], validated like the rows of every other format when
+    /// converted to a [`Transaction`]
+    pub(crate) fn record<C: Id, T: Id>(
+        &self,
+        index: usize,
+    ) -> Result<TransactionRow<C, T>, ReadError> {
+        TransactionRow::from_columns(
+            self.kind
+                .get(index)
+                .ok_or_else(|| ReadError::field("type", FieldError::Missing))?,
+            self.client.get("client", index)?,
+            self.tx.get("tx", index)?,
+            self.amount(index),
+            value(self.batch.as_ref(), index),
+            value(self.timestamp.as_ref(), index),
+        )
+    }
+
+    /// Read the row at `index` as a [`Transaction`] traced back to its row number, starting at 1, parsing amounts
+    /// stored as text according to the `locale` and applying the `precision` policy
+    pub(crate) fn transaction<C: Id, T: Id>(
+        &self,
+        index: usize,
+        locale: Locale,
+        precision: Precision,
+    ) -> Result<Transaction<C, T>, ReadError> {
+        let line = index as u64 + 1;
+
+        self.record(index)
+            .and_then(|row| {
+                row.into_transaction(locale, precision)
+                    .map_err(ReadError::invalid)
+            })
+            .map(|transaction| transaction.with_provenance(Provenance::new(None, line, None)))
+            .map_err(|e| e.at_line(line))
+    }
+
+    /// Text of the row at `index`, with the same layout as a CSV row
+    pub(crate) fn text(&self, index: usize) -> String {
+        let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+
+        [
+            self.kind.get(index).unwrap_or_default().to_string(),
+            self.client.text(index),
+            self.tx.text(index),
+            self.amount(index)
+                .map(|amount| amount.to_string())
+                .unwrap_or_default(),
+            optional(value(self.batch.as_ref(), index)),
+            optional(value(self.timestamp.as_ref(), index)),
+        ]
+        .join(",")
+    }
+}
+
+/// Value of an optional integer column at `index`
+fn value(column: Option<&UInt64Array>, index: usize) -> Option<u64> {
+    column
+        .filter(|column| column.is_valid(index))
+        .map(|column| column.value(index))
+}
+
+/// Cast a whole column to `data_type`
+fn cast(column: &ArrayRef, data_type: DataType) -> Result<ArrayRef, ArrowError> {
+    arrow_cast::cast(column, &data_type)
+}
+
+/// Text values of a column, cast to UTF-8 strings unless they already are
+fn text(column: &ArrayRef) -> Result<StringArray, ArrowError> {
+    match column.as_string_opt::<i32>() {
+        Some(column) => Ok(column.clone()),
+        None => Ok(cast(column, DataType::Utf8)?.as_string::<i32>().clone()),
+    }
+}
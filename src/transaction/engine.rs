@@ -1,21 +1,53 @@
-use std::collections::{hash_map::Entry, HashMap};
-
-use super::{Account, ClientId, Transaction, TransactionId, TransactionOperation};
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    str::FromStr,
+};
+
+use thiserror::Error;
+
+#[cfg(feature = "arrow")]
+use super::columnar::{Columns, RecordBatchError};
+use super::{
+    reorder::{ReorderBuffer, ReorderConfig},
+    Account, BatchId, ClientId, Id, Provenance, Transaction, TransactionId, TransactionKind,
+    TransactionOperation, TransactionRecord,
+};
+#[cfg(feature = "arrow")]
+use crate::input::amount::{Locale, Precision};
+
+/// State of a recorded transaction with regards to disputes
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum DisputeState {
+    /// The transaction is not disputed and can be disputed
+    Undisputed,
+
+    /// The transaction is disputed and its funds are held
+    Disputed,
+
+    /// The transaction has been reversed following a dispute and can no longer be disputed
+    ChargedBack,
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct TransactionEntry {
+    /// Whether the transaction was a deposit or a withdrawal
+    kind: TransactionKind,
+
     /// Amount of the transaction
     amount: f64,
 
-    /// Flag indicated whether a transaction has been disputed or not
-    disputed: bool,
-}
+    /// Whether the transaction has been disputed or not
+    state: DisputeState,
 
-#[derive(Debug)]
-struct ClientEntry {
-    /// Client that this entry refers to
-    id: ClientId,
+    /// Row of the input that the transaction has been read from, if known
+    provenance: Option<Provenance>,
+}
 
+/// Funds of a client account
+#[derive(Debug, Clone, Copy, Default)]
+struct Balance {
     /// The total funds that are available for trading, staking, withdrawal, etc
     available: f64,
 
@@ -27,105 +59,120 @@ struct ClientEntry {
 
     /// Whether the account is locked. An account is locked if a charge back occurs
     locked: bool,
-
-    /// Transactions that have been processed
-    transactions: HashMap<TransactionId, TransactionEntry>,
 }
 
-impl Into<Account> for ClientEntry {
-    fn into(self) -> Account {
-        Account {
-            client: self.id,
-            available: self.available,
-            held: self.held,
-            total: self.total,
-            locked: self.locked,
-        }
-    }
-}
-
-impl ClientEntry {
-    fn new(id: ClientId) -> Self {
-        Self {
-            id,
-            available: Default::default(),
-            held: Default::default(),
-            total: Default::default(),
-            locked: Default::default(),
-            transactions: Default::default(),
-        }
-    }
-
-    fn apply(&mut self, transaction: Transaction) -> Account {
-        let id = transaction.id;
+impl Balance {
+    /// Apply a transaction to the balance, recording deposits and withdrawals in the `ledger` of the client
+    /// Returns the [`Rejection`] reason if the transaction has been ignored
+    fn apply<C: Id, T: Id>(
+        &mut self,
+        ledger: &mut impl Ledger<T>,
+        transaction: &Transaction<C, T>,
+    ) -> Result<(), Rejection<C, T>> {
+        let Transaction { id, provenance, .. } = transaction;
 
         match transaction.operation {
             TransactionOperation::Deposit(amount) => {
-                if let Entry::Vacant(e) = self.transactions.entry(id) {
-                    self.available += amount;
-                    self.total += amount;
+                if ledger.contains(id) {
+                    return Err(Rejection::DuplicateTransaction(id.clone()));
+                }
+
+                self.available += amount;
+                self.total += amount;
 
-                    e.insert(TransactionEntry {
+                ledger.insert(
+                    id.clone(),
+                    TransactionEntry {
+                        kind: TransactionKind::Deposit,
                         amount,
-                        disputed: false,
-                    });
-                }
+                        state: DisputeState::Undisputed,
+                        provenance: provenance.clone(),
+                    },
+                );
             }
 
             TransactionOperation::Withdrawal(amount) => {
-                if let Entry::Vacant(e) = self.transactions.entry(id) {
-                    let available = self.available - amount;
-                    if available >= 0.0 {
-                        self.available = available;
-                        self.total -= amount;
-                    }
+                if ledger.contains(id) {
+                    return Err(Rejection::DuplicateTransaction(id.clone()));
+                }
 
-                    e.insert(TransactionEntry {
+                // The withdrawal is recorded even if funds are insufficient
+                ledger.insert(
+                    id.clone(),
+                    TransactionEntry {
+                        kind: TransactionKind::Withdrawal,
                         amount,
-                        disputed: false,
-                    });
+                        state: DisputeState::Undisputed,
+                        provenance: provenance.clone(),
+                    },
+                );
+
+                let available = self.available - amount;
+                if available >= 0.0 {
+                    self.available = available;
+                    self.total -= amount;
+                } else {
+                    return Err(Rejection::InsufficientFunds(id.clone()));
                 }
             }
 
             TransactionOperation::Dispute => {
-                if let Some(disputed_tx) = self.transactions.get_mut(&id) {
-                    if !disputed_tx.disputed {
-                        // TODO(oktal): unclear as to why the available amount must be decreased
-                        self.available -= disputed_tx.amount;
-                        self.held += disputed_tx.amount;
-                        disputed_tx.disputed = true;
-                    }
-                }
+                let disputed_tx = disputable(ledger, id, DisputeState::Undisputed)?;
+
+                // TODO(oktal): unclear as to why the available amount must be decreased
+                disputed_tx.state = DisputeState::Disputed;
+                let amount = disputed_tx.amount;
+                self.available -= amount;
+                self.held += amount;
             }
 
             TransactionOperation::Resolve => {
-                if let Some(disputed_tx) = self.transactions.get_mut(&id) {
-                    if disputed_tx.disputed {
-                        self.available += disputed_tx.amount;
-                        self.held -= disputed_tx.amount;
-                        disputed_tx.disputed = false;
-                    }
-                }
+                let disputed_tx = disputable(ledger, id, DisputeState::Disputed)?;
+
+                disputed_tx.state = DisputeState::Undisputed;
+                let amount = disputed_tx.amount;
+                self.available += amount;
+                self.held -= amount;
             }
 
             TransactionOperation::Chargeback => {
-                if let Some(disputed_tx) = self.transactions.get(&id) {
-                    if disputed_tx.disputed {
-                        self.held -= disputed_tx.amount;
-                        self.total -= disputed_tx.amount;
+                let disputed_tx = disputable(ledger, id, DisputeState::Disputed)?;
 
-                        self.locked = true;
-                    }
-                }
+                disputed_tx.state = DisputeState::ChargedBack;
+                let amount = disputed_tx.amount;
+                self.held -= amount;
+                self.total -= amount;
+
+                self.locked = true;
             }
         }
 
-        self.as_account()
+        Ok(())
+    }
+
+    /// Check that the invariants of the balance hold after a transaction has been applied
+    /// `was_locked` is whether the balance was locked before applying the transaction
+    fn check(&self, was_locked: bool) -> Result<(), Invariant> {
+        let tolerance = TOLERANCE * self.total.abs().max(1.0);
+
+        if (self.total - (self.available + self.held)).abs() > tolerance {
+            return Err(Invariant::Total);
+        }
+
+        if self.held < -tolerance {
+            return Err(Invariant::NonNegativeHeld);
+        }
+
+        if was_locked && !self.locked {
+            return Err(Invariant::StaysLocked);
+        }
+
+        Ok(())
     }
 
-    fn as_account(&self) -> Account {
+    fn as_account<C>(&self, client: ClientId<C>) -> Account<C> {
         Account {
-            client: self.id,
+            client,
             available: self.available,
             held: self.held,
             total: self.total,
@@ -134,50 +181,782 @@ impl ClientEntry {
     }
 }
 
+/// Deposit and withdrawal transactions recorded for a client, referenced by disputes, resolves and chargebacks
+trait Ledger<T> {
+    /// Whether the transaction `id` has been recorded
+    fn contains(&self, id: &TransactionId<T>) -> bool;
+
+    /// Retrieve the recorded transaction `id` to update its dispute state
+    fn get_mut(&mut self, id: &TransactionId<T>) -> Option<&mut TransactionEntry>;
+
+    /// Record the transaction `id`
+    fn insert(&mut self, id: TransactionId<T>, entry: TransactionEntry);
+}
+
+impl<T: Id> Ledger<T> for HashMap<TransactionId<T>, TransactionEntry> {
+    fn contains(&self, id: &TransactionId<T>) -> bool {
+        self.contains_key(id)
+    }
+
+    fn get_mut(&mut self, id: &TransactionId<T>) -> Option<&mut TransactionEntry> {
+        HashMap::get_mut(self, id)
+    }
+
+    fn insert(&mut self, id: TransactionId<T>, entry: TransactionEntry) {
+        HashMap::insert(self, id, entry);
+    }
+}
+
+/// Ledger of a client as seen by a batch. Transactions recorded or updated by the batch are kept in `changes`,
+/// on top of the transactions `committed` to the engine which are left untouched
+struct Staged<'a, T> {
+    committed: Option<&'a HashMap<TransactionId<T>, TransactionEntry>>,
+
+    changes: &'a mut HashMap<TransactionId<T>, TransactionEntry>,
+}
+
+impl<T: Id> Ledger<T> for Staged<'_, T> {
+    fn contains(&self, id: &TransactionId<T>) -> bool {
+        self.changes.contains_key(id) || self.committed.is_some_and(|c| c.contains_key(id))
+    }
+
+    fn get_mut(&mut self, id: &TransactionId<T>) -> Option<&mut TransactionEntry> {
+        // Only the transactions updated by the batch are copied
+        if !self.changes.contains_key(id) {
+            let entry = self.committed?.get(id)?.clone();
+            self.changes.insert(id.clone(), entry);
+        }
+
+        self.changes.get_mut(id)
+    }
+
+    fn insert(&mut self, id: TransactionId<T>, entry: TransactionEntry) {
+        self.changes.insert(id, entry);
+    }
+}
+
+/// Retrieve the recorded transaction `id` referenced by a dispute, resolve or chargeback
+/// that expects the transaction to be in the `expected` state
+fn disputable<'a, C: Id, T: Id>(
+    ledger: &'a mut impl Ledger<T>,
+    id: &TransactionId<T>,
+    expected: DisputeState,
+) -> Result<&'a mut TransactionEntry, Rejection<C, T>> {
+    let Some(transaction) = ledger.get_mut(id) else {
+        return Err(Rejection::UnknownTransaction(id.clone()));
+    };
+
+    if transaction.state != expected {
+        return Err(match expected {
+            DisputeState::Disputed => Rejection::NotDisputed(id.clone()),
+            _ => Rejection::NotDisputable(id.clone()),
+        });
+    }
+
+    Ok(transaction)
+}
+
+#[derive(Debug, Clone)]
+struct ClientEntry<C, T> {
+    /// Client that this entry refers to
+    id: ClientId<C>,
+
+    /// Funds of the client
+    balance: Balance,
+
+    /// Transactions that have been processed
+    transactions: HashMap<TransactionId<T>, TransactionEntry>,
+}
+
+impl<C: Id, T: Id> From<ClientEntry<C, T>> for Account<C> {
+    fn from(entry: ClientEntry<C, T>) -> Self {
+        entry.as_account()
+    }
+}
+
+impl<C: Id, T: Id> ClientEntry<C, T> {
+    fn new(id: ClientId<C>) -> Self {
+        Self {
+            id,
+            balance: Default::default(),
+            transactions: Default::default(),
+        }
+    }
+
+    fn as_account(&self) -> Account<C> {
+        self.balance.as_account(self.id.clone())
+    }
+
+    fn record_of(&self, id: &TransactionId<T>) -> Option<TransactionRecord<C, T>> {
+        self.transactions
+            .get(id)
+            .map(|transaction| self.as_record(id, transaction))
+    }
+
+    fn records(&self) -> impl Iterator<Item = TransactionRecord<C, T>> + '_ {
+        self.transactions
+            .iter()
+            .map(|(id, transaction)| self.as_record(id, transaction))
+    }
+
+    fn as_record(
+        &self,
+        id: &TransactionId<T>,
+        transaction: &TransactionEntry,
+    ) -> TransactionRecord<C, T> {
+        TransactionRecord {
+            client: self.id.clone(),
+            id: id.clone(),
+            kind: transaction.kind,
+            amount: transaction.amount,
+            disputed: transaction.state == DisputeState::Disputed,
+            provenance: transaction.provenance.clone(),
+        }
+    }
+
+    fn has_disputes(&self) -> bool {
+        self.transactions
+            .values()
+            .any(|t| t.state == DisputeState::Disputed)
+    }
+}
+
+/// Changes made by a batch to the account of a client, only applied to the engine once the batch is committed
+#[derive(Debug)]
+struct StagedClient<T> {
+    /// Funds of the client once the transactions of the batch processed so far have been applied
+    balance: Balance,
+
+    /// Transactions recorded or updated by the batch
+    transactions: HashMap<TransactionId<T>, TransactionEntry>,
+}
+
+impl<T> StagedClient<T> {
+    fn new(balance: Balance) -> Self {
+        Self {
+            balance,
+            transactions: HashMap::new(),
+        }
+    }
+}
+
+/// Relative tolerance used when comparing funds to account for floating point rounding errors
+const TOLERANCE: f64 = 1e-9;
+
+/// An invariant that must hold for every account after a transaction has been processed
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum Invariant {
+    /// The total funds must be equal to the sum of the available and held funds
+    Total,
+
+    /// The held funds must never be negative
+    NonNegativeHeld,
+
+    /// A locked account must stay locked
+    StaysLocked,
+}
+
+impl fmt::Display for Invariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Invariant::Total => write!(f, "total == available + held"),
+            Invariant::NonNegativeHeld => write!(f, "held >= 0"),
+            Invariant::StaysLocked => write!(f, "locked accounts stay locked"),
+        }
+    }
+}
+
+/// Raised when processing a transaction breaks an [`Invariant`]
+#[derive(Debug, Error)]
+#[error(
+    "invariant `{invariant}` violated by transaction {} of client {}{}: {account:?}",
+    transaction.id,
+    transaction.client,
+    origin(&transaction.provenance)
+)]
+pub struct InvariantViolation<C: Id = u16, T: Id = u32> {
+    /// The invariant that does not hold
+    pub invariant: Invariant,
+
+    /// The offending transaction
+    pub transaction: Transaction<C, T>,
+
+    /// The account after the offending transaction has been applied
+    pub account: Account<C>,
+}
+
+/// Reason for which a transaction has been rejected by the [`TransactionEngine`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Error)]
+#[non_exhaustive]
+pub enum Rejection<C: Id = u16, T: Id = u32> {
+    /// The client does not exist. Clients are created by their first deposit
+    #[error("client {0} does not exist")]
+    UnknownClient(ClientId<C>),
+
+    /// A deposit or withdrawal with the same identifier has already been processed
+    #[error("transaction {0} has already been processed")]
+    DuplicateTransaction(TransactionId<T>),
+
+    /// The available funds are insufficient for the withdrawal
+    #[error("insufficient funds for withdrawal {0}")]
+    InsufficientFunds(TransactionId<T>),
+
+    /// The transaction referenced by a dispute, resolve or chargeback does not exist
+    #[error("transaction {0} does not exist")]
+    UnknownTransaction(TransactionId<T>),
+
+    /// The transaction referenced by a dispute is already disputed or has been charged back
+    #[error("transaction {0} cannot be disputed")]
+    NotDisputable(TransactionId<T>),
+
+    /// The transaction referenced by a resolve or chargeback is not disputed
+    #[error("transaction {0} is not disputed")]
+    NotDisputed(TransactionId<T>),
+
+    /// The transaction referenced by a dispute, resolve or chargeback has not been seen yet.
+    /// The transaction has been parked in the reorder buffer and will be applied once the referenced transaction arrives
+    #[error("transaction {0} has not been seen yet, parked until it arrives")]
+    Parked(TransactionId<T>),
+
+    /// Another transaction of the batch that the transaction belongs to was rejected and the batch was rolled back
+    #[error("batch {0} has been rolled back")]
+    RolledBack(BatchId),
+}
+
+/// Raised when a batch of transactions has been rolled back because one of its transactions was rejected
+#[derive(Debug, Error)]
+#[error(
+    "transaction {} at index {index} of the batch{} was rejected: {rejection}",
+    transaction.id,
+    origin(&transaction.provenance)
+)]
+pub struct BatchError<C: Id = u16, T: Id = u32> {
+    /// Index of the rejected transaction in the batch
+    pub index: usize,
+
+    /// The rejected transaction
+    pub transaction: Transaction<C, T>,
+
+    /// Reason for which the transaction was rejected
+    pub rejection: Rejection<C, T>,
+}
+
+/// Tell where a transaction has been read from in an error message, if known
+fn origin(provenance: &Option<Provenance>) -> String {
+    provenance
+        .as_ref()
+        .map(|provenance| format!(" read from {provenance}"))
+        .unwrap_or_default()
+}
+
+/// Result of processing a single transaction
+type Processed<C, T> = Result<Account<C>, Rejection<C, T>>;
+
+/// Index of the transaction that rolled a batch back along with the reason for which it was rejected
+type Rejected<C, T> = (usize, Rejection<C, T>);
+
+/// Whether the operation is a dispute, resolve or chargeback that references another transaction
+fn is_dispute(operation: TransactionOperation) -> bool {
+    matches!(
+        operation,
+        TransactionOperation::Dispute
+            | TransactionOperation::Resolve
+            | TransactionOperation::Chargeback
+    )
+}
+
+/// Order in which accounts are retrieved from the [`TransactionEngine`]
+/// Accounts that compare equal are always ordered by client identifier to make the order deterministic
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum AccountOrder {
+    /// Order accounts by ascending client identifier
+    #[default]
+    Client,
+
+    /// Order accounts by ascending total funds
+    Total,
+
+    /// Order unlocked accounts first, then locked accounts
+    Status,
+}
+
+impl AccountOrder {
+    fn compare<C: Id, T: Id>(&self, lhs: &ClientEntry<C, T>, rhs: &ClientEntry<C, T>) -> Ordering {
+        let ordering = match self {
+            AccountOrder::Client => Ordering::Equal,
+            AccountOrder::Total => lhs.balance.total.total_cmp(&rhs.balance.total),
+            AccountOrder::Status => lhs.balance.locked.cmp(&rhs.balance.locked),
+        };
+
+        ordering.then_with(|| lhs.id.cmp(&rhs.id))
+    }
+}
+
+/// Error raised when parsing an unknown [`AccountOrder`]
+#[derive(Debug, Error)]
+#[error("unknown account order `{0}`, expected one of `client`, `total` or `status`")]
+pub struct ParseAccountOrderError(String);
+
+impl FromStr for AccountOrder {
+    type Err = ParseAccountOrderError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "client" => Ok(AccountOrder::Client),
+            "total" => Ok(AccountOrder::Total),
+            "status" => Ok(AccountOrder::Status),
+            _ => Err(ParseAccountOrderError(s.to_string())),
+        }
+    }
+}
+
 /// Main transaction engine that will process transactions
-pub struct TransactionEngine {
-    clients: HashMap<ClientId, ClientEntry>,
+///
+/// The engine is generic over the representation of client identifiers `C` and transaction identifiers `T`,
+/// see [`Id`]. The compact `u16` and `u32` representations are used by default
+pub struct TransactionEngine<C: Id = u16, T: Id = u32> {
+    clients: HashMap<ClientId<C>, ClientEntry<C, T>>,
+
+    /// Whether account invariants are verified after every processed transaction
+    verify: bool,
+
+    /// Buffer parking out-of-order dispute, resolve and chargeback transactions, if enabled
+    reorder: Option<ReorderBuffer<C, T>>,
+
+    /// Number of transactions processed so far
+    rows: u64,
+
+    /// Parked transactions applied once the transaction they reference arrived, along with the result of applying
+    /// them
+    released: Vec<(Transaction<C, T>, Processed<C, T>)>,
 }
 
-impl TransactionEngine {
+impl<C: Id, T: Id> Default for TransactionEngine<C, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Id, T: Id> TransactionEngine<C, T> {
     /// Create a new, empty transaction engine
     pub fn new() -> Self {
         Self {
             clients: HashMap::new(),
+            verify: false,
+            reorder: None,
+            rows: 0,
+            released: Vec::new(),
         }
     }
 
+    /// Enable a reorder buffer.
+    /// Dispute, resolve and chargeback transactions referencing a client or transaction that has not been seen yet
+    /// are parked instead of being ignored and applied once the referenced transaction arrives.
+    /// Parked transactions that exceed the bounds of the `config` expire and can be retrieved with
+    /// [`Self::take_expired`]
+    pub fn with_reorder_buffer(mut self, config: ReorderConfig) -> Self {
+        self.reorder = Some(ReorderBuffer::new(config));
+        self
+    }
+
+    /// Enable or disable the verification mode.
+    /// In verification mode, the engine asserts that every [`Invariant`] holds after every processed transaction
+    /// and panics with an [`InvariantViolation`] reporting the offending transaction otherwise
+    pub fn with_verification(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
     /// Process a transaction
     /// Returns the [`Account`] associated with the client of the transaction if the client for which the
     /// transaction should be applied exist or [`None`] otherwise
-    pub fn process(&mut self, transaction: Transaction) -> Option<Account> {
-        let entry = match transaction.operation {
-            TransactionOperation::Deposit(_) => Some(
-                self.clients
-                    .entry(transaction.client)
-                    .or_insert_with_key(|id| ClientEntry::new(*id)),
-            ),
-
-            _ => self.clients.get_mut(&transaction.client),
+    ///
+    /// # Panics
+    ///
+    /// Panics if verification is enabled and the transaction violates an [`Invariant`]
+    pub fn process(&mut self, transaction: Transaction<C, T>) -> Option<Account<C>> {
+        let client = transaction.client.clone();
+
+        match self.try_process(transaction) {
+            Ok(account) => Some(account),
+            Err(Rejection::UnknownClient(_)) => None,
+            Err(_) => self.account(&client),
+        }
+    }
+
+    /// Process a transaction
+    /// Returns the [`Account`] associated with the client of the transaction or the [`Rejection`] reason
+    /// if the transaction has been ignored
+    ///
+    /// # Panics
+    ///
+    /// Panics if verification is enabled and the transaction violates an [`Invariant`]
+    pub fn try_process(
+        &mut self,
+        transaction: Transaction<C, T>,
+    ) -> Result<Account<C>, Rejection<C, T>> {
+        self.advance();
+        let result = self.process_one(&transaction);
+
+        match (&mut self.reorder, result) {
+            (
+                Some(reorder),
+                Err(Rejection::UnknownClient(_) | Rejection::UnknownTransaction(_)),
+            ) if is_dispute(transaction.operation) => {
+                let id = transaction.id.clone();
+                reorder.park(transaction, self.rows);
+                Err(Rejection::Parked(id))
+            }
+            (_, result) => result,
+        }
+    }
+
+    /// Count a transaction being processed, expiring the parked transactions that exceeded the bounds of the
+    /// reorder buffer
+    fn advance(&mut self) {
+        self.rows += 1;
+        if let Some(reorder) = &mut self.reorder {
+            reorder.expire(self.rows);
+        }
+    }
+
+    /// Expire all the transactions parked in the reorder buffer, typically once all the input has been processed
+    pub fn expire_parked(&mut self) {
+        if let Some(reorder) = &mut self.reorder {
+            reorder.expire_all();
+        }
+    }
+
+    /// Take the dispute, resolve and chargeback transactions that expired in the reorder buffer before the
+    /// transaction they reference arrived
+    pub fn take_expired(&mut self) -> Vec<Transaction<C, T>> {
+        self.reorder
+            .as_mut()
+            .map(ReorderBuffer::take_expired)
+            .unwrap_or_default()
+    }
+
+    /// Take the dispute, resolve and chargeback transactions released from the reorder buffer since the last call,
+    /// in the order they have been applied once the transaction they reference arrived, along with the result of
+    /// applying them. Released transactions can still be rejected, such as a second dispute of the same transaction
+    pub fn take_released(&mut self) -> Vec<(Transaction<C, T>, Processed<C, T>)> {
+        std::mem::take(&mut self.released)
+    }
+
+    fn process_one(
+        &mut self,
+        transaction: &Transaction<C, T>,
+    ) -> Result<Account<C>, Rejection<C, T>> {
+        let client = &transaction.client;
+
+        // Clients are created by their first deposit
+        if matches!(transaction.operation, TransactionOperation::Deposit(_))
+            && !self.clients.contains_key(client)
+        {
+            self.clients
+                .insert(client.clone(), ClientEntry::new(client.clone()));
+        }
+        let Some(entry) = self.clients.get_mut(client) else {
+            return Err(Rejection::UnknownClient(client.clone()));
+        };
+
+        let result = Self::apply(
+            &mut entry.balance,
+            &mut entry.transactions,
+            transaction,
+            self.verify,
+        );
+
+        // Only the transactions that have been applied can release the transactions parked waiting for them
+        if result.is_err() || !self.record(transaction) {
+            return result;
+        }
+
+        // Parked transactions released by this transaction might have updated the account
+        result.map(|account| self.account(client).unwrap_or(account))
+    }
+
+    /// Process a batch of transactions atomically.
+    /// Only the balances of the affected accounts and the transactions recorded or disputed by the batch are
+    /// staged, and committed if no transaction of the batch is rejected. Otherwise, the whole batch is rolled back
+    /// and the engine is left untouched
+    ///
+    /// Transactions of a batch are never parked in the reorder buffer, which would apply them outside of their batch:
+    /// a dispute, resolve or chargeback referencing a transaction that has not been seen yet rolls the batch back.
+    /// Every transaction of the batch counts towards the expiry of the transactions parked so far
+    ///
+    /// Returns the [`Account`] associated with the client of every transaction of the batch or a [`BatchError`]
+    /// reporting the first rejected transaction
+    ///
+    /// # Panics
+    ///
+    /// Panics if verification is enabled and a transaction violates an [`Invariant`]
+    pub fn process_batch(
+        &mut self,
+        transactions: impl IntoIterator<Item = Transaction<C, T>>,
+    ) -> Result<Vec<Account<C>>, BatchError<C, T>> {
+        let mut transactions = transactions.into_iter().collect::<Vec<_>>();
+
+        self.apply_batch(&transactions)
+            .map_err(|(index, rejection)| BatchError {
+                index,
+                transaction: transactions.swap_remove(index),
+                rejection,
+            })
+    }
+
+    /// Atomically apply the `transactions` of a batch, counting every one of them towards the expiry of the
+    /// transactions parked so far
+    /// Returns the [`Account`] associated with the client of every transaction or the index of the first rejected
+    /// transaction along with the [`Rejection`] reason
+    fn apply_batch(
+        &mut self,
+        transactions: &[Transaction<C, T>],
+    ) -> Result<Vec<Account<C>>, Rejected<C, T>> {
+        let mut staged = HashMap::new();
+        let mut accounts = Vec::with_capacity(transactions.len());
+
+        for (index, transaction) in transactions.iter().enumerate() {
+            self.advance();
+            let account = self
+                .stage(&mut staged, transaction)
+                .map_err(|rejection| (index, rejection))?;
+
+            accounts.push(account);
+        }
+
+        self.commit(staged);
+        for transaction in transactions {
+            self.record(transaction);
+        }
+
+        Ok(accounts)
+    }
+
+    /// Apply a transaction of a batch to the `staged` changes of the batch, leaving the engine untouched
+    fn stage(
+        &self,
+        staged: &mut HashMap<ClientId<C>, StagedClient<T>>,
+        transaction: &Transaction<C, T>,
+    ) -> Result<Account<C>, Rejection<C, T>> {
+        let committed = self.clients.get(&transaction.client);
+
+        let client = match staged.entry(transaction.client.clone()) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => match (committed, transaction.operation) {
+                (Some(entry), _) => e.insert(StagedClient::new(entry.balance)),
+                (None, TransactionOperation::Deposit(_)) => {
+                    e.insert(StagedClient::new(Balance::default()))
+                }
+                (None, _) => return Err(Rejection::UnknownClient(transaction.client.clone())),
+            },
         };
-        entry.map(|e| e.apply(transaction))
+
+        let mut ledger = Staged {
+            committed: committed.map(|entry| &entry.transactions),
+            changes: &mut client.transactions,
+        };
+        Self::apply(&mut client.balance, &mut ledger, transaction, self.verify)
+    }
+
+    /// Apply the `staged` changes of a batch to the engine
+    fn commit(&mut self, staged: HashMap<ClientId<C>, StagedClient<T>>) {
+        for (id, client) in staged {
+            let entry = self
+                .clients
+                .entry(id)
+                .or_insert_with_key(|id| ClientEntry::new(id.clone()));
+
+            entry.balance = client.balance;
+            entry.transactions.extend(client.transactions);
+        }
+    }
+
+    /// Process all the rows of an Arrow [`RecordBatch`](arrow_array::RecordBatch) with `type`, `client`, `tx`,
+    /// `amount` and optional `batch` and `timestamp` columns, reading every column as a typed array once for the
+    /// whole record batch. Rows are validated like the rows of every other format, parsing amounts stored as text
+    /// according to the `locale` and applying the `precision` policy.
+    /// Rows are processed one by one, except consecutive rows sharing the same `batch` id which are processed
+    /// atomically like with [`Self::process_batch`]. Transactions are traced back to their row, starting at 1
+    ///
+    /// This entry point is meant for library users already holding record batches in memory: the command line
+    /// reads Arrow files row by row with the [`ArrowReader`](crate::input::ArrowReader) instead, like every other
+    /// format, so that malformed rows are reported individually
+    ///
+    /// Returns the result of processing every row or an error, before any row is processed, if a row is malformed.
+    /// When a batch is rolled back, the row at fault is rejected with its own reason and the other rows of the batch
+    /// with [`Rejection::RolledBack`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if verification is enabled and a transaction violates an [`Invariant`]
+    #[cfg(feature = "arrow")]
+    pub fn process_record_batch(
+        &mut self,
+        batch: &arrow_array::RecordBatch,
+        locale: Locale,
+        precision: Precision,
+    ) -> Result<Vec<Processed<C, T>>, RecordBatchError> {
+        let columns = Columns::new(batch)?;
+        let transactions = (0..columns.len())
+            .map(|index| columns.transaction(index, locale, precision))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(RecordBatchError::InvalidRow)?;
+
+        let mut processed = Vec::with_capacity(transactions.len());
+        let mut transactions = transactions.into_iter().peekable();
+
+        while let Some(transaction) = transactions.next() {
+            let Some(batch) = transaction.batch else {
+                processed.push(self.try_process(transaction));
+                continue;
+            };
+
+            let mut rows = vec![transaction];
+            while let Some(row) = transactions.next_if(|row| row.batch == Some(batch)) {
+                rows.push(row);
+            }
+            processed.extend(self.process_rows(&rows, batch));
+        }
+
+        Ok(processed)
+    }
+
+    /// Atomically process the `rows` of a record batch that belong to the same `batch`
+    #[cfg(feature = "arrow")]
+    fn process_rows(&mut self, rows: &[Transaction<C, T>], batch: BatchId) -> Vec<Processed<C, T>> {
+        match self.apply_batch(rows) {
+            Ok(accounts) => accounts.into_iter().map(Ok).collect(),
+            Err((index, rejection)) => {
+                let mut rejected = vec![Err(Rejection::RolledBack(batch)); rows.len()];
+                rejected[index] = Err(rejection);
+                rejected
+            }
+        }
+    }
+
+    /// Apply a transaction to the `balance` and `ledger` of its client, verifying the invariants of the balance if
+    /// `verify` is enabled
+    fn apply(
+        balance: &mut Balance,
+        ledger: &mut impl Ledger<T>,
+        transaction: &Transaction<C, T>,
+        verify: bool,
+    ) -> Result<Account<C>, Rejection<C, T>> {
+        let was_locked = balance.locked;
+        let result = balance.apply(ledger, transaction);
+        let account = balance.as_account(transaction.client.clone());
+
+        if verify {
+            if let Err(invariant) = balance.check(was_locked) {
+                panic!(
+                    "{}",
+                    InvariantViolation {
+                        invariant,
+                        transaction: transaction.clone(),
+                        account,
+                    }
+                );
+            }
+        }
+
+        result.map(|()| account)
+    }
+
+    /// Apply the transactions that were parked waiting for a deposit or withdrawal transaction
+    /// Returns whether parked transactions have been released
+    fn record(&mut self, transaction: &Transaction<C, T>) -> bool {
+        if is_dispute(transaction.operation) {
+            return false;
+        }
+
+        let released = self
+            .reorder
+            .as_mut()
+            .map(|reorder| reorder.release(&transaction.client, &transaction.id))
+            .unwrap_or_default();
+
+        let any = !released.is_empty();
+        for parked in released {
+            let result = self.process_one(&parked);
+            self.released.push((parked, result));
+        }
+
+        any
+    }
+
+    /// Retrieve an iterator over all the current [`Account`] accounts in the given `order`
+    /// Identical sequences of transactions always yield accounts in the same order
+    pub fn accounts(&self, order: AccountOrder) -> impl Iterator<Item = Account<C>> + '_ {
+        Self::ordered(self.clients.values(), order)
     }
 
-    /// Retrieve an iterator over all the current [`Account`] accounts
-    pub fn accounts<'a>(&'a self) -> impl Iterator<Item = Account> + 'a {
-        self.clients.values().map(ClientEntry::as_account)
+    /// Retrieve the current [`Account`] of a client
+    /// Returns [`None`] if the client does not exist
+    pub fn account(&self, client: &ClientId<C>) -> Option<Account<C>> {
+        self.clients.get(client).map(ClientEntry::as_account)
     }
 
-    #[cfg(test)]
-    fn account_of(&self, client: ClientId) -> Option<Account> {
-        self.clients.get(&client).map(ClientEntry::as_account)
+    /// Retrieve an iterator over the deposit and withdrawal transactions recorded for a client
+    /// along with their dispute state.
+    /// Returns [`None`] if the client does not exist
+    pub fn transactions(
+        &self,
+        client: &ClientId<C>,
+    ) -> Option<impl Iterator<Item = TransactionRecord<C, T>> + '_> {
+        self.clients.get(client).map(ClientEntry::records)
+    }
+
+    /// Find a deposit or withdrawal transaction of a client by its identifier. Transaction identifiers are only
+    /// unique per client
+    /// Returns [`None`] if no such transaction has been recorded for the client
+    pub fn transaction(
+        &self,
+        client: &ClientId<C>,
+        id: &TransactionId<T>,
+    ) -> Option<TransactionRecord<C, T>> {
+        self.clients.get(client)?.record_of(id)
+    }
+
+    /// Retrieve an iterator over the accounts that currently have at least one disputed transaction,
+    /// ordered by client identifier
+    pub fn disputed_accounts(&self) -> impl Iterator<Item = Account<C>> + '_ {
+        Self::ordered(
+            self.clients.values().filter(|e| e.has_disputes()),
+            AccountOrder::Client,
+        )
+    }
+
+    /// Retrieve an iterator over the accounts that have been locked following a chargeback,
+    /// ordered by client identifier
+    pub fn locked_accounts(&self) -> impl Iterator<Item = Account<C>> + '_ {
+        Self::ordered(
+            self.clients.values().filter(|e| e.balance.locked),
+            AccountOrder::Client,
+        )
+    }
+
+    /// Sort client entries in the given `order`
+    /// Only references to the entries are buffered, accounts are created lazily when iterating
+    fn ordered<'a>(
+        entries: impl Iterator<Item = &'a ClientEntry<C, T>>,
+        order: AccountOrder,
+    ) -> impl Iterator<Item = Account<C>> + 'a {
+        let mut entries = entries.collect::<Vec<_>>();
+        entries.sort_unstable_by(|lhs, rhs| order.compare(lhs, rhs));
+        entries.into_iter().map(ClientEntry::as_account)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::transaction::ClientId;
+    use crate::{
+        output::{CsvWriter, Writer},
+        transaction::{reorder::ReorderConfig, ClientId},
+    };
 
     const BOB: ClientId = ClientId(1);
     const ALICE: ClientId = ClientId(2);
@@ -189,11 +968,11 @@ mod tests {
 
         // Deposit to bob's account
         let account = engine
-            .process(Transaction {
-                client: BOB,
-                id: TransactionId(1),
-                operation: TransactionOperation::Deposit(10.0),
-            })
+            .process(Transaction::new(
+                BOB,
+                TransactionId(1),
+                TransactionOperation::Deposit(10.0),
+            ))
             .expect("bob's account should exist after deposit");
 
         // Make sure bob's account have been deposited with 10.0
@@ -208,7 +987,7 @@ mod tests {
         assert!(!account.locked);
 
         // Make sure ALICE does not exist
-        assert!(matches!(engine.account_of(ALICE), None));
+        assert!(engine.account(&ALICE).is_none());
     }
 
     #[test]
@@ -218,20 +997,20 @@ mod tests {
 
         // Deposit to bob's account
         engine
-            .process(Transaction {
-                client: BOB,
-                id: TransactionId(1),
-                operation: TransactionOperation::Deposit(10.0),
-            })
+            .process(Transaction::new(
+                BOB,
+                TransactionId(1),
+                TransactionOperation::Deposit(10.0),
+            ))
             .expect("bob's account should exist after deposit");
 
         // Attempt to double deposit the same transaction to bob's account
         let account = engine
-            .process(Transaction {
-                client: BOB,
-                id: TransactionId(1),
-                operation: TransactionOperation::Deposit(10.0),
-            })
+            .process(Transaction::new(
+                BOB,
+                TransactionId(1),
+                TransactionOperation::Deposit(10.0),
+            ))
             .expect("bob's account should exist after deposit");
 
         // Make sure the amount has not been deposited twice
@@ -248,19 +1027,19 @@ mod tests {
         const PAYCHECK: f64 = 100.0;
 
         // Deposit paycheck to Bob's account
-        engine.process(Transaction {
-            client: BOB,
-            id: TransactionId(1),
-            operation: TransactionOperation::Deposit(PAYCHECK),
-        });
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
 
         // Withdraw half the paycheck for taxes
         let account = engine
-            .process(Transaction {
-                client: BOB,
-                id: TransactionId(2),
-                operation: TransactionOperation::Withdrawal(PAYCHECK / 2.0),
-            })
+            .process(Transaction::new(
+                BOB,
+                TransactionId(2),
+                TransactionOperation::Withdrawal(PAYCHECK / 2.0),
+            ))
             .expect("bob's account should exist after withdrawing from an existing account");
 
         // Make sure bob's account has been withdrawn
@@ -283,21 +1062,21 @@ mod tests {
         const PAYCHECK: f64 = 100.0;
 
         // Deposit paycheck to Bob's account
-        engine.process(Transaction {
-            client: BOB,
-            id: TransactionId(1),
-            operation: TransactionOperation::Deposit(PAYCHECK),
-        });
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
 
         // Withdraw from Alice account
-        let account = engine.process(Transaction {
-            client: ALICE,
-            id: TransactionId(2),
-            operation: TransactionOperation::Withdrawal(PAYCHECK / 2.0),
-        });
+        let account = engine.process(Transaction::new(
+            ALICE,
+            TransactionId(2),
+            TransactionOperation::Withdrawal(PAYCHECK / 2.0),
+        ));
 
         // Make sure the account does not exist for Alice
-        assert!(matches!(account, None));
+        assert!(account.is_none());
     }
 
     #[test]
@@ -308,19 +1087,19 @@ mod tests {
         const PAYCHECK: f64 = 100.0;
 
         // Deposit paycheck to Bob's account
-        engine.process(Transaction {
-            client: BOB,
-            id: TransactionId(1),
-            operation: TransactionOperation::Deposit(PAYCHECK),
-        });
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
 
         // Withdraw twice the paycheck to pay rent
         let account = engine
-            .process(Transaction {
-                client: BOB,
-                id: TransactionId(2),
-                operation: TransactionOperation::Withdrawal(PAYCHECK * 2.0),
-            })
+            .process(Transaction::new(
+                BOB,
+                TransactionId(2),
+                TransactionOperation::Withdrawal(PAYCHECK * 2.0),
+            ))
             .expect("bob's account should exist after withdrawing from an existing account");
 
         // Make sure bob's account has not been withdrawn
@@ -337,19 +1116,19 @@ mod tests {
         const PAYCHECK: f64 = 100.0;
 
         // Deposit paycheck to Bob's account
-        engine.process(Transaction {
-            client: BOB,
-            id: TransactionId(1),
-            operation: TransactionOperation::Deposit(PAYCHECK),
-        });
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
 
         // Attempt to dispute an unknown transaction from Bob
         let account = engine
-            .process(Transaction {
-                client: BOB,
-                id: TransactionId(100),
-                operation: TransactionOperation::Dispute,
-            })
+            .process(Transaction::new(
+                BOB,
+                TransactionId(100),
+                TransactionOperation::Dispute,
+            ))
             .expect("Bob's account should exist after depositing");
 
         // Make sure nothing has been disputed
@@ -367,21 +1146,21 @@ mod tests {
         const PAYCHECK: f64 = 100.0;
 
         // Deposit paycheck to Bob's account
-        engine.process(Transaction {
-            client: BOB,
-            id: TransactionId(1),
-            operation: TransactionOperation::Deposit(PAYCHECK),
-        });
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
 
         // Attempt to dispute Alice' account
-        let account = engine.process(Transaction {
-            client: ALICE,
-            id: TransactionId(1),
-            operation: TransactionOperation::Dispute,
-        });
+        let account = engine.process(Transaction::new(
+            ALICE,
+            TransactionId(1),
+            TransactionOperation::Dispute,
+        ));
 
         // Make sure disputed account does not exist
-        assert!(matches!(account, None));
+        assert!(account.is_none());
     }
 
     #[test]
@@ -391,26 +1170,26 @@ mod tests {
 
         const PAYCHECK: f64 = 100.0;
         // Deposit paycheck to Bob's account
-        engine.process(Transaction {
-            client: BOB,
-            id: TransactionId(1),
-            operation: TransactionOperation::Deposit(PAYCHECK),
-        });
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
 
         // Bob has been scammed, withdraw everything
-        engine.process(Transaction {
-            client: BOB,
-            id: TransactionId(2),
-            operation: TransactionOperation::Withdrawal(PAYCHECK),
-        });
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(2),
+            TransactionOperation::Withdrawal(PAYCHECK),
+        ));
 
         // Bob realized he's been scammed, dispute the transaction
         let account = engine
-            .process(Transaction {
-                client: BOB,
-                id: TransactionId(2),
-                operation: TransactionOperation::Dispute,
-            })
+            .process(Transaction::new(
+                BOB,
+                TransactionId(2),
+                TransactionOperation::Dispute,
+            ))
             .expect("Bob's account should exist after depositing");
 
         // Make sure the funds are held in bob's account
@@ -425,35 +1204,35 @@ mod tests {
 
         const PAYCHECK: f64 = 100.0;
         // Deposit paycheck to Bob's account
-        engine.process(Transaction {
-            client: BOB,
-            id: TransactionId(1),
-            operation: TransactionOperation::Deposit(PAYCHECK),
-        });
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
 
         // Bob has been scammed, withdraw everything
-        engine.process(Transaction {
-            client: BOB,
-            id: TransactionId(2),
-            operation: TransactionOperation::Withdrawal(PAYCHECK),
-        });
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(2),
+            TransactionOperation::Withdrawal(PAYCHECK),
+        ));
 
         // Bob realized he's been scammed, dispute the transaction
         engine
-            .process(Transaction {
-                client: BOB,
-                id: TransactionId(2),
-                operation: TransactionOperation::Dispute,
-            })
+            .process(Transaction::new(
+                BOB,
+                TransactionId(2),
+                TransactionOperation::Dispute,
+            ))
             .expect("Bob's account should exist after depositing");
 
         // Bank investigated and decided to give funds back to bob
         let account = engine
-            .process(Transaction {
-                client: BOB,
-                id: TransactionId(2),
-                operation: TransactionOperation::Resolve,
-            })
+            .process(Transaction::new(
+                BOB,
+                TransactionId(2),
+                TransactionOperation::Resolve,
+            ))
             .expect("bob's account should exist after depositing");
 
         // Make sure the dispute has been resolved
@@ -462,4 +1241,701 @@ mod tests {
         // TODO(oktal): this check fails because we decrement the available amount
         // assert_eq!(account.available, PAYCHECK);
     }
+
+    #[test]
+    fn query_transactions() {
+        // Setup
+        let mut engine = TransactionEngine::new();
+
+        const PAYCHECK: f64 = 100.0;
+        // Deposit paycheck to Bob's account
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
+
+        // Bob pays his rent
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(2),
+            TransactionOperation::Withdrawal(PAYCHECK / 2.0),
+        ));
+
+        // Bob disputes his rent
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(2),
+            TransactionOperation::Dispute,
+        ));
+
+        // Make sure both transactions are recorded for bob along with their dispute state
+        let mut transactions = engine
+            .transactions(&BOB)
+            .expect("bob's account should exist after depositing")
+            .collect::<Vec<_>>();
+        transactions.sort_by_key(|t| t.id);
+
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0].kind, TransactionKind::Deposit);
+        assert!(!transactions[0].disputed);
+        assert_eq!(transactions[1].kind, TransactionKind::Withdrawal);
+        assert_eq!(transactions[1].amount, PAYCHECK / 2.0);
+        assert!(transactions[1].disputed);
+
+        // Make sure the transaction can be found by its id
+        let rent = engine
+            .transaction(&BOB, &TransactionId(2))
+            .expect("bob's rent should have been recorded");
+        assert_eq!(rent.client, BOB);
+        assert!(rent.disputed);
+
+        // Make sure unknown clients and transactions are not found
+        assert!(engine.transactions(&ALICE).is_none());
+        assert!(engine.transaction(&BOB, &TransactionId(3)).is_none());
+        assert!(engine.transaction(&ALICE, &TransactionId(2)).is_none());
+
+        // Make sure bob's account is reported as disputed but not locked
+        let disputed = engine.disputed_accounts().collect::<Vec<_>>();
+        assert_eq!(disputed.len(), 1);
+        assert_eq!(disputed[0].client, BOB);
+        assert_eq!(engine.locked_accounts().count(), 0);
+    }
+
+    #[test]
+    fn query_transactions_reusing_ids() {
+        // Setup
+        let mut engine = TransactionEngine::new();
+
+        // Bob and Alice both deposit a transaction with the same id, then Alice deposits it again
+        for (client, amount) in [(BOB, 10.0), (ALICE, 20.0), (ALICE, 30.0)] {
+            engine.process(Transaction::new(
+                client,
+                TransactionId(1),
+                TransactionOperation::Deposit(amount),
+            ));
+        }
+
+        // Make sure each client finds its own transaction
+        for (client, amount) in [(BOB, 10.0), (ALICE, 20.0)] {
+            let deposit = engine
+                .transaction(&client, &TransactionId(1))
+                .expect("the deposit should have been recorded");
+            assert_eq!((deposit.client, deposit.amount), (client, amount));
+        }
+    }
+
+    #[test]
+    fn query_locked_accounts() {
+        // Setup
+        let mut engine = TransactionEngine::new();
+
+        const PAYCHECK: f64 = 100.0;
+        // Deposit paycheck to Bob's and Alice's accounts
+        for (id, client) in [BOB, ALICE].into_iter().enumerate() {
+            engine.process(Transaction::new(
+                client,
+                TransactionId(id as u32),
+                TransactionOperation::Deposit(PAYCHECK),
+            ));
+        }
+
+        // Bob disputes his paycheck and charges it back
+        for operation in [
+            TransactionOperation::Dispute,
+            TransactionOperation::Chargeback,
+        ] {
+            engine.process(Transaction::new(BOB, TransactionId(0), operation));
+        }
+
+        // Make sure only bob's account is locked
+        let locked = engine.locked_accounts().collect::<Vec<_>>();
+        assert_eq!(locked.len(), 1);
+        assert_eq!(locked[0].client, BOB);
+        assert!(locked[0].locked);
+        assert!(engine.account(&ALICE).is_some_and(|a| !a.locked));
+    }
+
+    #[test]
+    fn accounts_order() {
+        // Setup
+        let mut engine = TransactionEngine::new();
+
+        // Deposit decreasing amounts to increasing clients
+        for client in 1..=10u16 {
+            engine.process(Transaction::new(
+                ClientId(client),
+                TransactionId(client.into()),
+                TransactionOperation::Deposit(100.0 - client as f64),
+            ));
+        }
+
+        // Charge back the deposit of the first client to lock its account
+        for operation in [
+            TransactionOperation::Dispute,
+            TransactionOperation::Chargeback,
+        ] {
+            engine.process(Transaction::new(ClientId(1), TransactionId(1), operation));
+        }
+
+        let clients = |order| {
+            engine
+                .accounts(order)
+                .map(|a| a.client.0)
+                .collect::<Vec<_>>()
+        };
+
+        // Make sure accounts are sorted according to the requested order
+        assert_eq!(clients(AccountOrder::Client), (1..=10).collect::<Vec<_>>());
+        assert_eq!(
+            clients(AccountOrder::Total),
+            [1, 10, 9, 8, 7, 6, 5, 4, 3, 2]
+        );
+        assert_eq!(
+            clients(AccountOrder::Status),
+            [2, 3, 4, 5, 6, 7, 8, 9, 10, 1]
+        );
+    }
+
+    #[test]
+    fn accounts_output_is_deterministic() {
+        let output = || {
+            let mut engine = TransactionEngine::<u16, u32>::new();
+            for client in 1..=100u16 {
+                engine.process(Transaction::new(
+                    ClientId(client),
+                    TransactionId(client.into()),
+                    TransactionOperation::Deposit(client as f64 / 3.0),
+                ));
+            }
+
+            let mut writer = CsvWriter::new(Vec::new()).expect("writer should be created");
+            for account in engine.accounts(AccountOrder::Client) {
+                writer.write(account).expect("account should be written");
+            }
+
+            writer.into_inner().expect("writer should be flushed")
+        };
+
+        // Make sure identical input produces byte-identical output across engines
+        assert_eq!(output(), output());
+    }
+
+    #[test]
+    fn double_chargeback() {
+        // Setup
+        let mut engine = TransactionEngine::new().with_verification(true);
+
+        const PAYCHECK: f64 = 100.0;
+        // Deposit paycheck to Bob's account
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
+
+        // Bob disputes his paycheck and charges it back twice
+        let mut account = None;
+        for operation in [
+            TransactionOperation::Dispute,
+            TransactionOperation::Chargeback,
+            TransactionOperation::Chargeback,
+        ] {
+            account = engine.process(Transaction::new(BOB, TransactionId(1), operation));
+        }
+
+        // Make sure the paycheck has only been charged back once
+        let account = account.expect("bob's account should exist after depositing");
+        assert_eq!(account.total, 0.0);
+        assert_eq!(account.held, 0.0);
+        assert!(account.locked);
+    }
+
+    #[test]
+    #[should_panic(expected = "invariant `total == available + held` violated by transaction 2")]
+    fn verification_reports_offending_transaction() {
+        // Setup
+        let mut engine = TransactionEngine::new().with_verification(true);
+
+        // Deposit to bob's account
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(10.0),
+        ));
+
+        // Corrupt bob's account
+        engine
+            .clients
+            .get_mut(&BOB)
+            .expect("bob's account should exist after deposit")
+            .balance
+            .total = 0.0;
+
+        // Make sure the next transaction reports the broken invariant
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(2),
+            TransactionOperation::Deposit(10.0),
+        ));
+    }
+
+    #[test]
+    fn try_process_rejections() {
+        // Setup
+        let mut engine = TransactionEngine::new();
+
+        const PAYCHECK: f64 = 100.0;
+        let transaction = |id, operation| Transaction::new(BOB, TransactionId(id), operation);
+
+        // Withdrawing from bob before he deposited anything
+        assert_eq!(
+            engine
+                .try_process(transaction(1, TransactionOperation::Withdrawal(PAYCHECK)))
+                .err(),
+            Some(Rejection::UnknownClient(BOB))
+        );
+
+        // Deposit paycheck to Bob's account, twice
+        assert!(engine
+            .try_process(transaction(1, TransactionOperation::Deposit(PAYCHECK)))
+            .is_ok());
+        assert_eq!(
+            engine
+                .try_process(transaction(1, TransactionOperation::Deposit(PAYCHECK)))
+                .err(),
+            Some(Rejection::DuplicateTransaction(TransactionId(1)))
+        );
+
+        // Withdraw more than available
+        assert_eq!(
+            engine
+                .try_process(transaction(
+                    2,
+                    TransactionOperation::Withdrawal(PAYCHECK * 2.0)
+                ))
+                .err(),
+            Some(Rejection::InsufficientFunds(TransactionId(2)))
+        );
+
+        // Dispute flow on unknown and undisputed transactions
+        assert_eq!(
+            engine
+                .try_process(transaction(3, TransactionOperation::Dispute))
+                .err(),
+            Some(Rejection::UnknownTransaction(TransactionId(3)))
+        );
+        assert_eq!(
+            engine
+                .try_process(transaction(1, TransactionOperation::Resolve))
+                .err(),
+            Some(Rejection::NotDisputed(TransactionId(1)))
+        );
+        assert!(engine
+            .try_process(transaction(1, TransactionOperation::Dispute))
+            .is_ok());
+        assert_eq!(
+            engine
+                .try_process(transaction(1, TransactionOperation::Dispute))
+                .err(),
+            Some(Rejection::NotDisputable(TransactionId(1)))
+        );
+    }
+
+    #[test]
+    fn batch_commit() {
+        // Setup
+        let mut engine = TransactionEngine::new();
+
+        const PAYCHECK: f64 = 100.0;
+
+        // Deposit paychecks to Bob's and Alice's accounts and have Bob pay his rent in a single batch
+        let accounts = engine
+            .process_batch([
+                Transaction::new(
+                    BOB,
+                    TransactionId(1),
+                    TransactionOperation::Deposit(PAYCHECK),
+                ),
+                Transaction::new(
+                    ALICE,
+                    TransactionId(2),
+                    TransactionOperation::Deposit(PAYCHECK),
+                ),
+                Transaction::new(
+                    BOB,
+                    TransactionId(3),
+                    TransactionOperation::Withdrawal(PAYCHECK / 2.0),
+                ),
+            ])
+            .expect("batch should be committed");
+
+        // Make sure the batch returns the account after each transaction
+        assert_eq!(accounts.len(), 3);
+        assert_eq!(accounts[0].total, PAYCHECK);
+        assert_eq!(accounts[2].total, PAYCHECK / 2.0);
+
+        // Make sure the batch has been committed
+        assert!(engine
+            .account(&BOB)
+            .is_some_and(|a| a.total == PAYCHECK / 2.0));
+        assert!(engine.account(&ALICE).is_some_and(|a| a.total == PAYCHECK));
+        assert!(engine
+            .transaction(&BOB, &TransactionId(3))
+            .is_some_and(|t| t.client == BOB));
+    }
+
+    #[test]
+    fn batch_rollback() {
+        // Setup
+        let mut engine = TransactionEngine::new();
+
+        const PAYCHECK: f64 = 100.0;
+        // Deposit paycheck to Bob's account
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
+
+        // Deposit to Alice and Bob then have bob withdraw more than available
+        let error = engine
+            .process_batch([
+                Transaction::new(
+                    ALICE,
+                    TransactionId(2),
+                    TransactionOperation::Deposit(PAYCHECK),
+                ),
+                Transaction::new(
+                    BOB,
+                    TransactionId(3),
+                    TransactionOperation::Deposit(PAYCHECK),
+                ),
+                Transaction::new(
+                    BOB,
+                    TransactionId(4),
+                    TransactionOperation::Withdrawal(PAYCHECK * 3.0),
+                ),
+            ])
+            .expect_err("batch should be rolled back");
+
+        // Make sure the offending transaction is reported
+        assert_eq!(error.index, 2);
+        assert_eq!(error.transaction.id, TransactionId(4));
+        assert_eq!(
+            error.rejection,
+            Rejection::InsufficientFunds(TransactionId(4))
+        );
+
+        // Make sure nothing from the batch has been applied
+        assert!(engine.account(&ALICE).is_none());
+        assert!(engine.account(&BOB).is_some_and(|a| a.total == PAYCHECK));
+        assert!(engine.transaction(&BOB, &TransactionId(3)).is_none());
+    }
+
+    #[test]
+    fn batch_rollback_keeps_committed_transactions() {
+        // Setup
+        let mut engine = TransactionEngine::new();
+
+        const PAYCHECK: f64 = 100.0;
+        // Deposit paycheck to Bob's account
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
+
+        // Dispute the paycheck then withdraw more than available in the same batch
+        engine
+            .process_batch([
+                Transaction::new(BOB, TransactionId(1), TransactionOperation::Dispute),
+                Transaction::new(
+                    BOB,
+                    TransactionId(2),
+                    TransactionOperation::Withdrawal(PAYCHECK),
+                ),
+            ])
+            .expect_err("batch should be rolled back");
+
+        // Make sure the paycheck is not disputed anymore
+        let paycheck = engine
+            .transaction(&BOB, &TransactionId(1))
+            .expect("paycheck should have been recorded");
+        assert!(!paycheck.disputed);
+        assert!(engine.account(&BOB).is_some_and(|a| a.held == 0.0));
+
+        // Make sure the dispute applies once committed
+        engine
+            .process_batch([Transaction::new(
+                BOB,
+                TransactionId(1),
+                TransactionOperation::Dispute,
+            )])
+            .expect("batch should be committed");
+        assert!(engine
+            .transaction(&BOB, &TransactionId(1))
+            .is_some_and(|t| t.disputed));
+        assert!(engine.account(&BOB).is_some_and(|a| a.held == PAYCHECK));
+    }
+
+    #[test]
+    fn batch_excluded_from_reorder_buffer() {
+        // Setup
+        let mut engine = TransactionEngine::new().with_reorder_buffer(ReorderConfig {
+            max_rows: Some(2),
+            max_age: None,
+        });
+
+        const PAYCHECK: f64 = 100.0;
+
+        // Bob disputes a paycheck that has not arrived yet
+        assert_eq!(
+            engine
+                .try_process(Transaction::new(
+                    BOB,
+                    TransactionId(1),
+                    TransactionOperation::Dispute
+                ))
+                .err(),
+            Some(Rejection::Parked(TransactionId(1)))
+        );
+
+        // Make sure a dispute of a batch referencing an unknown transaction rolls the batch back
+        let error = engine
+            .process_batch([
+                Transaction::new(
+                    BOB,
+                    TransactionId(2),
+                    TransactionOperation::Deposit(PAYCHECK),
+                ),
+                Transaction::new(BOB, TransactionId(3), TransactionOperation::Dispute),
+            ])
+            .expect_err("batch should be rolled back");
+        assert_eq!(
+            error.rejection,
+            Rejection::UnknownTransaction(TransactionId(3))
+        );
+        assert!(engine.take_expired().is_empty());
+
+        // Make sure every transaction of a batch counts towards the expiry of parked transactions
+        engine
+            .process_batch([Transaction::new(
+                BOB,
+                TransactionId(2),
+                TransactionOperation::Deposit(PAYCHECK),
+            )])
+            .expect("batch should be committed");
+        let expired = engine.take_expired();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, TransactionId(1));
+    }
+
+    #[test]
+    fn trace_transactions_to_their_rows() {
+        // Setup
+        let mut engine = TransactionEngine::new();
+        let file = Some(std::sync::Arc::from("transactions.csv"));
+        let row = |line, byte| Provenance::new(file.clone(), line, byte);
+
+        // Deposit paycheck to Bob's account, read from the second line of a file
+        engine.process(
+            Transaction::new(BOB, TransactionId(1), TransactionOperation::Deposit(100.0))
+                .with_provenance(row(2, Some(26))),
+        );
+
+        // Make sure the recorded transaction can be traced back to its row
+        let paycheck = engine
+            .transaction(&BOB, &TransactionId(1))
+            .expect("bob's paycheck should have been recorded");
+        assert_eq!(paycheck.provenance, Some(row(2, Some(26))));
+
+        // Make sure a rolled back batch reports the row of the offending transaction
+        let error = engine
+            .process_batch([Transaction::new(
+                BOB,
+                TransactionId(2),
+                TransactionOperation::Withdrawal(300.0),
+            )
+            .with_provenance(row(3, None))])
+            .expect_err("batch should be rolled back");
+        assert_eq!(
+            error.to_string(),
+            "transaction 2 at index 0 of the batch read from transactions.csv:3 was rejected: \
+             insufficient funds for withdrawal 2"
+        );
+    }
+
+    #[test]
+    fn reorder_dispute_before_deposit() {
+        // Setup
+        let mut engine = TransactionEngine::new().with_reorder_buffer(ReorderConfig {
+            max_rows: Some(10),
+            max_age: None,
+        });
+
+        const PAYCHECK: f64 = 100.0;
+
+        // Bob disputes his paycheck before it has been deposited
+        let account = engine.try_process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Dispute,
+        ));
+        assert_eq!(account.err(), Some(Rejection::Parked(TransactionId(1))));
+
+        // Deposit paycheck to Bob's account
+        let account = engine
+            .process(Transaction::new(
+                BOB,
+                TransactionId(1),
+                TransactionOperation::Deposit(PAYCHECK),
+            ))
+            .expect("bob's account should exist after deposit");
+
+        // Make sure the dispute has been applied once the deposit arrived
+        assert_eq!(account.held, PAYCHECK);
+        assert_eq!(account.available, 0.0);
+        assert!(engine.take_expired().is_empty());
+    }
+
+    #[test]
+    fn reorder_expired() {
+        // Setup
+        let mut engine = TransactionEngine::new().with_reorder_buffer(ReorderConfig {
+            max_rows: Some(2),
+            max_age: None,
+        });
+
+        const PAYCHECK: f64 = 100.0;
+
+        // Bob disputes a paycheck that will arrive too late and one that will never arrive
+        for id in [1, 2] {
+            engine.process(Transaction::new(
+                BOB,
+                TransactionId(id),
+                TransactionOperation::Dispute,
+            ));
+        }
+
+        // Deposit unrelated funds to Alice's account until the first dispute expires
+        for id in [10, 11] {
+            engine.process(Transaction::new(
+                ALICE,
+                TransactionId(id),
+                TransactionOperation::Deposit(PAYCHECK),
+            ));
+        }
+
+        let expired = engine.take_expired();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, TransactionId(1));
+
+        // Deposit the first paycheck after its dispute expired
+        let account = engine
+            .process(Transaction::new(
+                BOB,
+                TransactionId(1),
+                TransactionOperation::Deposit(PAYCHECK),
+            ))
+            .expect("bob's account should exist after deposit");
+
+        // Make sure the expired dispute has not been applied
+        assert_eq!(account.held, 0.0);
+
+        // Make sure the remaining dispute expires at the end of the input
+        engine.expire_parked();
+        let expired = engine.take_expired();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, TransactionId(2));
+    }
+
+    #[test]
+    fn reorder_released_rejections() {
+        // Setup
+        let mut engine = TransactionEngine::new().with_reorder_buffer(ReorderConfig {
+            max_rows: Some(10),
+            max_age: None,
+        });
+
+        const PAYCHECK: f64 = 100.0;
+
+        // Bob disputes his paycheck twice and his rent once before they arrive
+        for id in [1, 1, 2] {
+            engine.process(Transaction::new(
+                BOB,
+                TransactionId(id),
+                TransactionOperation::Dispute,
+            ));
+        }
+
+        // Deposit paycheck to Bob's account
+        engine.process(Transaction::new(
+            BOB,
+            TransactionId(1),
+            TransactionOperation::Deposit(PAYCHECK),
+        ));
+
+        // Make sure the second dispute is rejected once released
+        let released = engine.take_released();
+        assert_eq!(released.len(), 2);
+        assert!(released[0].1.is_ok());
+        assert_eq!(
+            released[1].1.as_ref().err(),
+            Some(&Rejection::NotDisputable(TransactionId(1)))
+        );
+
+        // Make sure a rejected withdrawal does not release the dispute waiting for it
+        assert_eq!(
+            engine
+                .try_process(Transaction::new(
+                    BOB,
+                    TransactionId(2),
+                    TransactionOperation::Withdrawal(PAYCHECK * 2.0)
+                ))
+                .err(),
+            Some(Rejection::InsufficientFunds(TransactionId(2)))
+        );
+        assert!(engine.take_released().is_empty());
+        assert!(engine.account(&BOB).is_some_and(|a| a.held == PAYCHECK));
+
+        engine.expire_parked();
+        let expired = engine.take_expired();
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].id, TransactionId(2));
+    }
+
+    #[test]
+    fn opaque_identifiers() {
+        // Setup
+        let mut engine = TransactionEngine::<String, String>::new();
+
+        const PAYCHECK: f64 = 100.0;
+        let bob = ClientId::from("5b1e7c4e-bob".to_string());
+        let paycheck = TransactionId::from("0d6c9a7e-4f7b-4c1d-9b9e-2f0c1e8a7d3b".to_string());
+
+        // Deposit paycheck to Bob's account and dispute it
+        for operation in [
+            TransactionOperation::Deposit(PAYCHECK),
+            TransactionOperation::Dispute,
+        ] {
+            engine.process(Transaction::new(bob.clone(), paycheck.clone(), operation));
+        }
+
+        // Make sure the paycheck is held and can be found by its id
+        let account = engine
+            .account(&bob)
+            .expect("bob's account should exist after deposit");
+        assert_eq!(account.held, PAYCHECK);
+        assert!(engine
+            .transaction(&bob, &paycheck)
+            .is_some_and(|t| t.client == bob && t.disputed));
+
+        // Identifiers give their value back
+        assert_eq!(account.client.get(), "5b1e7c4e-bob");
+        assert_eq!(
+            paycheck.into_inner(),
+            "0d6c9a7e-4f7b-4c1d-9b9e-2f0c1e8a7d3b"
+        );
+    }
 }
@@ -1,6 +1,14 @@
-use serde::{Deserialize, Serialize};
+use std::{fmt, hash::Hash, str::FromStr, sync::Arc};
 
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[cfg(feature = "arrow")]
+pub mod columnar;
 pub mod engine;
+pub mod reorder;
+
+#[cfg(test)]
+mod proptests;
 
 /// Represents a type of transaction handled by the payment engine
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -23,46 +31,224 @@ pub enum TransactionOperation {
     Chargeback,
 }
 
+/// Maximum number of decimal places of an amount
+pub const MAX_DECIMALS: usize = 4;
+
+/// Whether `amount` has at most [`MAX_DECIMALS`] decimal places, that is whether it is the closest number to a
+/// multiple of `10^-MAX_DECIMALS`
+pub(crate) fn is_precise(amount: f64) -> bool {
+    let scale = 10f64.powi(MAX_DECIMALS as i32);
+    let scaled = amount * scale;
+
+    // Beyond 2^53, consecutive numbers are further apart than 10^-MAX_DECIMALS once scaled back
+    scaled.abs() >= 2f64.powi(53) || scaled.round() / scale == amount
+}
+
+/// A type that can be used as the underlying representation of a [`ClientId`] or a [`TransactionId`].
+///
+/// Small integers such as `u16` and `u32` keep identifiers compact, while wider integers such as `u64`
+/// or opaque strings such as UUIDs can be used when the compact representation is not large enough.
+/// Identifiers are [`Send`] so that records can be parsed on several threads
+pub trait Id:
+    Clone
+    + Eq
+    + Ord
+    + Hash
+    + fmt::Debug
+    + fmt::Display
+    + FromStr
+    + Serialize
+    + DeserializeOwned
+    + Send
+    + 'static
+{
+    /// The identifier as an `i64` if it is represented by an integer type such as `u16` or `u32` and fits in an
+    /// `i64`. Identifiers represented as text are never integers, even when they read like one such as `"007"`
+    fn as_i64(&self) -> Option<i64> {
+        serde_json::to_value(self).ok()?.as_i64()
+    }
+}
+
+impl<T> Id for T where
+    T: Clone
+        + Eq
+        + Ord
+        + Hash
+        + fmt::Debug
+        + fmt::Display
+        + FromStr
+        + Serialize
+        + DeserializeOwned
+        + Send
+        + 'static
+{
+}
+
 /// A unique identifier for a client that identifies a client's identity
 #[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub struct ClientId(u16);
+pub struct ClientId<C = u16>(C);
+
+impl<C> ClientId<C> {
+    /// Value of the identifier
+    pub fn get(&self) -> &C {
+        &self.0
+    }
 
-impl From<u16> for ClientId {
-    fn from(value: u16) -> Self {
+    /// Convert the identifier to its value
+    pub fn into_inner(self) -> C {
+        self.0
+    }
+}
+
+impl<C> From<C> for ClientId<C> {
+    fn from(value: C) -> Self {
         Self(value)
     }
 }
 
+impl<C: fmt::Display> fmt::Display for ClientId<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
 /// A unique identifier for a transaction
 #[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
-pub struct TransactionId(u32);
+pub struct TransactionId<T = u32>(T);
 
-impl From<u32> for TransactionId {
-    fn from(value: u32) -> Self {
+impl<T> TransactionId<T> {
+    /// Value of the identifier
+    pub fn get(&self) -> &T {
+        &self.0
+    }
+
+    /// Convert the identifier to its value
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for TransactionId<T> {
+    fn from(value: T) -> Self {
         Self(value)
     }
 }
 
+impl<T: fmt::Display> fmt::Display for TransactionId<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// An identifier grouping transactions that must be applied atomically
+#[derive(Debug, Clone, Copy, Eq, PartialEq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct BatchId(u64);
+
+impl From<u64> for BatchId {
+    fn from(value: u64) -> Self {
+        Self(value)
+    }
+}
+
+impl fmt::Display for BatchId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// Origin of a transaction in the input it has been read from, to trace the transaction back to its row
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Provenance {
+    /// Input that the transaction has been read from, if known. Shared by every transaction of the input
+    pub file: Option<Arc<str>>,
+
+    /// Line at which the row of the transaction starts, starting at 1, or the row number for formats that are not
+    /// line-based
+    pub line: u64,
+
+    /// Offset in bytes at which the row of the transaction starts, for formats that tell it
+    pub byte: Option<u64>,
+}
+
+impl Provenance {
+    /// Provenance of the row starting at `line` and `byte` of the input `file`
+    pub fn new(file: Option<Arc<str>>, line: u64, byte: Option<u64>) -> Self {
+        Self { file, line, byte }
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{file}:{}", self.line)?,
+            None => write!(f, "line {}", self.line)?,
+        }
+
+        match self.byte {
+            Some(byte) => write!(f, " (byte {byte})"),
+            None => Ok(()),
+        }
+    }
+}
+
 /// Represents a transaction that occured for a particular client
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
-pub struct Transaction {
+///
+/// New fields may be added to transactions in future versions, use [`Transaction::new`] to build one
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Transaction<C = u16, T = u32> {
     /// Client identifier
-    pub client: ClientId,
+    pub client: ClientId<C>,
 
     /// Transaction identifier
     /// A transaction id can either be unique for deposit and withdrawal transactions
     /// or represent a reference to an other transaction for other transaction types
-    pub id: TransactionId,
+    pub id: TransactionId<T>,
 
     /// The operation conveyed by the transaction
     pub operation: TransactionOperation,
+
+    /// Batch that this transaction belongs to, if any
+    /// Consecutive transactions that belong to the same batch must be applied all-or-nothing
+    pub batch: Option<BatchId>,
+
+    /// Row of the input that the transaction has been read from, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+impl<C, T> Transaction<C, T> {
+    /// Create a new transaction of `client` that does not belong to any batch
+    pub fn new(client: ClientId<C>, id: TransactionId<T>, operation: TransactionOperation) -> Self {
+        Self {
+            client,
+            id,
+            operation,
+            batch: None,
+            provenance: None,
+        }
+    }
+
+    /// Make the transaction belong to the given `batch`
+    pub fn with_batch(mut self, batch: BatchId) -> Self {
+        self.batch = Some(batch);
+        self
+    }
+
+    /// Trace the transaction back to the row of the input it has been read from
+    pub fn with_provenance(mut self, provenance: Provenance) -> Self {
+        self.provenance = Some(provenance);
+        self
+    }
 }
 
 /// Represents an account for a particular client
 #[derive(Debug, Copy, Clone, Serialize, Deserialize)]
-pub struct Account {
+#[non_exhaustive]
+pub struct Account<C = u16> {
     /// Client that this account is associated with
-    pub client: ClientId,
+    pub client: ClientId<C>,
 
     /// The total funds that are available for trading, staking, withdrawal, etc
     pub available: f64,
@@ -76,3 +262,40 @@ pub struct Account {
     /// Whether the account is locked. An account is locked if a charge back occurs
     pub locked: bool,
 }
+
+/// Kind of a transaction that has been recorded by the payment engine
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransactionKind {
+    /// A credit to the client's asset account
+    Deposit,
+
+    /// A debit to the client's asset account
+    Withdrawal,
+}
+
+/// Represents a transaction that has been recorded for a particular client along with its dispute state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct TransactionRecord<C = u16, T = u32> {
+    /// Client that this transaction has been recorded for
+    pub client: ClientId<C>,
+
+    /// Transaction identifier
+    #[serde(rename = "tx")]
+    pub id: TransactionId<T>,
+
+    /// Whether the transaction was a deposit or a withdrawal
+    #[serde(rename = "type")]
+    pub kind: TransactionKind,
+
+    /// Amount of the transaction
+    pub amount: f64,
+
+    /// Whether the transaction is currently disputed
+    pub disputed: bool,
+
+    /// Row of the input that the transaction has been read from, if known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
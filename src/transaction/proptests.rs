@@ -0,0 +1,208 @@
+//! Property-based tests running random sequences of transactions against the [`TransactionEngine`]
+//! and a simple reference model of the payment engine
+
+use std::collections::HashMap;
+
+use proptest::prelude::*;
+
+use super::{
+    engine::{AccountOrder, TransactionEngine},
+    Account, ClientId, Transaction, TransactionId, TransactionOperation,
+};
+
+/// Tolerance used when comparing the funds of the engine and the model
+const TOLERANCE: f64 = 1e-6;
+
+/// A transaction recorded by the reference model
+struct ModelTransaction {
+    id: TransactionId,
+    amount: f64,
+    disputed: bool,
+    charged_back: bool,
+}
+
+/// A client account of the reference model
+#[derive(Default)]
+struct ModelAccount {
+    available: f64,
+    held: f64,
+    locked: bool,
+    transactions: Vec<ModelTransaction>,
+}
+
+/// A straightforward, unoptimized implementation of the payment engine rules
+#[derive(Default)]
+struct Model {
+    accounts: HashMap<ClientId, ModelAccount>,
+}
+
+impl Model {
+    fn process(&mut self, transaction: Transaction) {
+        let account = match transaction.operation {
+            TransactionOperation::Deposit(_) => {
+                Some(self.accounts.entry(transaction.client).or_default())
+            }
+            _ => self.accounts.get_mut(&transaction.client),
+        };
+        let Some(account) = account else {
+            return;
+        };
+
+        let recorded = account
+            .transactions
+            .iter_mut()
+            .find(|t| t.id == transaction.id);
+
+        match (transaction.operation, recorded) {
+            (TransactionOperation::Deposit(amount), None) => {
+                account.available += amount;
+                account.record(transaction.id, amount);
+            }
+
+            (TransactionOperation::Withdrawal(amount), None) => {
+                if account.available >= amount {
+                    account.available -= amount;
+                }
+                account.record(transaction.id, amount);
+            }
+
+            (TransactionOperation::Dispute, Some(t)) if !t.disputed && !t.charged_back => {
+                t.disputed = true;
+                account.available -= t.amount;
+                account.held += t.amount;
+            }
+
+            (TransactionOperation::Resolve, Some(t)) if t.disputed => {
+                t.disputed = false;
+                account.available += t.amount;
+                account.held -= t.amount;
+            }
+
+            (TransactionOperation::Chargeback, Some(t)) if t.disputed => {
+                t.disputed = false;
+                t.charged_back = true;
+                account.held -= t.amount;
+                account.locked = true;
+            }
+
+            _ => {}
+        }
+    }
+}
+
+impl ModelAccount {
+    fn record(&mut self, id: TransactionId, amount: f64) {
+        self.transactions.push(ModelTransaction {
+            id,
+            amount,
+            disputed: false,
+            charged_back: false,
+        });
+    }
+}
+
+fn operation() -> impl Strategy<Value = TransactionOperation> {
+    // Amounts have at most four decimal places
+    let amount = (1u32..=1_000_000).prop_map(|a| a as f64 / 10_000.0);
+
+    prop_oneof![
+        3 => amount.clone().prop_map(TransactionOperation::Deposit),
+        2 => amount.prop_map(TransactionOperation::Withdrawal),
+        2 => Just(TransactionOperation::Dispute),
+        1 => Just(TransactionOperation::Resolve),
+        1 => Just(TransactionOperation::Chargeback),
+    ]
+}
+
+fn transaction() -> impl Strategy<Value = Transaction> {
+    // Keep the identifier spaces small so that transactions frequently reference each other
+    (1u16..=4, 1u32..=16, operation())
+        .prop_map(|(client, id, operation)| Transaction::new(client.into(), id.into(), operation))
+}
+
+fn assert_same(account: &Account, expected: &ModelAccount) {
+    assert!((account.available - expected.available).abs() < TOLERANCE);
+    assert!((account.held - expected.held).abs() < TOLERANCE);
+    assert!((account.total - (expected.available + expected.held)).abs() < TOLERANCE);
+    assert_eq!(account.locked, expected.locked);
+}
+
+/// Snapshot of all the accounts of an engine that can be compared
+fn snapshot(engine: &TransactionEngine) -> Vec<String> {
+    engine
+        .accounts(AccountOrder::Client)
+        .map(|account| format!("{account:?}"))
+        .collect()
+}
+
+proptest! {
+    #[test]
+    fn engine_matches_model(transactions in prop::collection::vec(transaction(), 0..200)) {
+        // Invariants are asserted by the engine after every transaction
+        let mut engine = TransactionEngine::new().with_verification(true);
+        let mut model = Model::default();
+
+        for transaction in transactions {
+            engine.process(transaction.clone());
+            model.process(transaction);
+        }
+
+        let accounts = engine.accounts(AccountOrder::Client).collect::<Vec<_>>();
+        prop_assert_eq!(accounts.len(), model.accounts.len());
+
+        for account in &accounts {
+            let expected = model
+                .accounts
+                .get(&account.client)
+                .expect("model should have the same clients as the engine");
+            assert_same(account, expected);
+        }
+    }
+
+    #[test]
+    fn locked_accounts_stay_locked(transactions in prop::collection::vec(transaction(), 0..200)) {
+        let mut engine = TransactionEngine::new().with_verification(true);
+        let mut locked = Vec::new();
+
+        for transaction in transactions {
+            engine.process(transaction);
+
+            for client in &locked {
+                prop_assert!(engine.account(client).is_some_and(|a| a.locked));
+            }
+
+            locked = engine.locked_accounts().map(|a| a.client).collect();
+        }
+    }
+
+    #[test]
+    fn batch_is_atomic(
+        transactions in prop::collection::vec(transaction(), 0..50),
+        batch in prop::collection::vec(transaction(), 0..10),
+    ) {
+        // Process the same transactions in two engines
+        let mut engine = TransactionEngine::new().with_verification(true);
+        let mut sequential = TransactionEngine::new().with_verification(true);
+        for transaction in transactions {
+            engine.process(transaction.clone());
+            sequential.process(transaction);
+        }
+
+        let before = snapshot(&engine);
+
+        // Apply the batch atomically to the first engine and sequentially to the second one
+        let committed = engine.process_batch(batch.clone()).is_ok();
+        let accepted = batch
+            .into_iter()
+            .all(|transaction| sequential.try_process(transaction).is_ok());
+
+        // A batch is committed if and only if all its transactions are accepted
+        prop_assert_eq!(committed, accepted);
+
+        if committed {
+            prop_assert_eq!(snapshot(&engine), snapshot(&sequential));
+        } else {
+            prop_assert_eq!(snapshot(&engine), before);
+        }
+    }
+}
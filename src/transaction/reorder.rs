@@ -0,0 +1,158 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use super::{ClientId, Id, Transaction, TransactionId};
+
+/// Configuration of the reorder buffer of the [`super::engine::TransactionEngine`]
+/// A parked transaction expires once any of the configured bounds is exceeded
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct ReorderConfig {
+    /// Maximum number of rows processed by the engine while a transaction stays parked
+    pub max_rows: Option<u64>,
+
+    /// Maximum duration a transaction stays parked
+    pub max_age: Option<Duration>,
+}
+
+/// Client and transaction referenced by a parked transaction
+type Key<C, T> = (ClientId<C>, TransactionId<T>);
+
+/// A dispute, resolve or chargeback transaction waiting for the transaction it references
+#[derive(Debug)]
+struct Parked<C, T> {
+    /// Sequence number of the parked transaction, used to track expiry
+    seq: u64,
+
+    /// Row at which the transaction has been parked
+    row: u64,
+
+    /// Time at which the transaction has been parked
+    at: Instant,
+
+    transaction: Transaction<C, T>,
+}
+
+/// Buffer that parks dispute, resolve and chargeback transactions referencing transactions that have not been
+/// seen yet, until the referenced transaction arrives or the parked transaction expires
+#[derive(Debug)]
+pub(super) struct ReorderBuffer<C, T> {
+    config: ReorderConfig,
+
+    /// Parked transactions, in arrival order, by the client and transaction they reference
+    parked: HashMap<Key<C, T>, VecDeque<Parked<C, T>>>,
+
+    /// Sequence number and key of every parked transaction in arrival order
+    queue: VecDeque<(u64, Key<C, T>)>,
+
+    /// Transactions that expired before the transaction they reference arrived
+    expired: Vec<Transaction<C, T>>,
+
+    /// Sequence number of the next parked transaction
+    seq: u64,
+}
+
+impl<C: Id, T: Id> ReorderBuffer<C, T> {
+    pub(super) fn new(config: ReorderConfig) -> Self {
+        Self {
+            config,
+            parked: HashMap::new(),
+            queue: VecDeque::new(),
+            expired: Vec::new(),
+            seq: 0,
+        }
+    }
+
+    /// Park a `transaction` that referenced an unknown transaction at the given `row`
+    pub(super) fn park(&mut self, transaction: Transaction<C, T>, row: u64) {
+        let key = (transaction.client.clone(), transaction.id.clone());
+        let seq = self.seq;
+        self.seq += 1;
+
+        self.parked
+            .entry(key.clone())
+            .or_default()
+            .push_back(Parked {
+                seq,
+                row,
+                at: Instant::now(),
+                transaction,
+            });
+        self.queue.push_back((seq, key));
+    }
+
+    /// Release the transactions, in arrival order, waiting for the transaction `id` of `client`
+    pub(super) fn release(
+        &mut self,
+        client: &ClientId<C>,
+        id: &TransactionId<T>,
+    ) -> Vec<Transaction<C, T>> {
+        if self.parked.is_empty() {
+            return Vec::new();
+        }
+
+        self.parked
+            .remove(&(client.clone(), id.clone()))
+            .map(|parked| parked.into_iter().map(|p| p.transaction).collect())
+            .unwrap_or_default()
+    }
+
+    /// Expire the transactions that exceeded the configured bounds at the given `row`
+    pub(super) fn expire(&mut self, row: u64) {
+        let now = Instant::now();
+
+        while let Some((seq, key)) = self.queue.front() {
+            let seq = *seq;
+            let Some(parked) = self.parked.get_mut(key) else {
+                // Already released
+                self.queue.pop_front();
+                continue;
+            };
+
+            // Transactions waiting for the same key are parked in arrival order.
+            // If the oldest one does not match, the queued one has already been released
+            let Some(oldest) = parked.front().filter(|p| p.seq == seq) else {
+                self.queue.pop_front();
+                continue;
+            };
+
+            let expired_rows = self
+                .config
+                .max_rows
+                .is_some_and(|max| row.saturating_sub(oldest.row) > max);
+            let expired_age = self
+                .config
+                .max_age
+                .is_some_and(|max| now.duration_since(oldest.at) >= max);
+
+            if !expired_rows && !expired_age {
+                break;
+            }
+
+            if let Some(oldest) = parked.pop_front() {
+                self.expired.push(oldest.transaction);
+            }
+            if parked.is_empty() {
+                self.parked.remove(key);
+            }
+            self.queue.pop_front();
+        }
+    }
+
+    /// Expire all the parked transactions
+    pub(super) fn expire_all(&mut self) {
+        self.queue.clear();
+
+        let mut parked = self.parked.drain().flat_map(|(_, p)| p).collect::<Vec<_>>();
+        parked.sort_by_key(|p| p.seq);
+
+        self.expired
+            .extend(parked.into_iter().map(|p| p.transaction));
+    }
+
+    /// Take the transactions that expired so far, in expiry order
+    pub(super) fn take_expired(&mut self) -> Vec<Transaction<C, T>> {
+        std::mem::take(&mut self.expired)
+    }
+}